@@ -0,0 +1,140 @@
+// compares the bitboard against the cell by cell board it replaced, run with `cargo bench`
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use tetris::board::{self, Board, PieceMask};
+use tetris::rotation::RotationSystemKind;
+use tetris::{PieceKind, PieceMap, SpaceState};
+
+const KINDS: [PieceKind; 7] = [
+    PieceKind::I,
+    PieceKind::O,
+    PieceKind::T,
+    PieceKind::S,
+    PieceKind::Z,
+    PieceKind::J,
+    PieceKind::L,
+];
+
+// the old representation, a SpaceState per cell and pieces as block maps
+struct CellBoard {
+    space: [[SpaceState; 22]; 10],
+}
+
+impl CellBoard {
+    fn fits(&self, map: &PieceMap, position: (i32, i32)) -> bool {
+        for (i, row) in map.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                if let SpaceState::FallingTetromino = cell {
+                    let x = position.0 + j as i32;
+                    let y = position.1 - i as i32;
+                    if x < 0
+                        || x >= self.space.len() as i32
+                        || y < 0
+                        || y >= self.space[0].len() as i32
+                    {
+                        return false;
+                    }
+                    if let SpaceState::SettledTetromino(_) = self.space[x as usize][y as usize] {
+                        return false;
+                    }
+                }
+            }
+        }
+        return true;
+    }
+
+    fn full_rows(&self) -> Vec<i32> {
+        let mut rows = Vec::new();
+        for y in 0..self.space[0].len() {
+            let full =
+                (0..self.space.len()).all(|x| !matches!(self.space[x][y], SpaceState::Empty));
+            if full {
+                rows.push(y as i32);
+            }
+        }
+        return rows;
+    }
+}
+
+// a ragged stack eight rows high with a well, the same for both boards
+fn stack() -> (Board, CellBoard) {
+    let mut bits = Board::new(10, 22);
+    let mut cells = CellBoard {
+        space: [[SpaceState::Empty; 22]; 10],
+    };
+    for x in 0..10 {
+        let height = if x == 9 { 0 } else { 4 + (x * 7) % 5 };
+        for y in 0..height {
+            bits.place(&[1, 0, 0, 0, 0], (x as i32, y as i32), PieceKind::Garbage);
+            cells.space[x][y] = SpaceState::SettledTetromino(PieceKind::Garbage);
+        }
+    }
+    return (bits, cells);
+}
+
+fn pieces() -> Vec<(PieceMap, PieceMask)> {
+    let srs = RotationSystemKind::Srs.build();
+    let mut pieces = Vec::new();
+    for &kind in KINDS.iter() {
+        for rotation in 0..4 {
+            let map = srs.map(kind, rotation);
+            pieces.push((map, board::mask_from_map(&map)));
+        }
+    }
+    return pieces;
+}
+
+// every rotation in every column dropped straight down from the top, what a bot's move
+// generator does for each candidate placement
+fn placements(c: &mut Criterion) {
+    let (bits, cells) = stack();
+    let pieces = pieces();
+    let mut group = c.benchmark_group("placements");
+    group.bench_function("cells", |b| {
+        b.iter(|| {
+            let mut landed = 0;
+            for (map, _) in pieces.iter() {
+                for x in -3..10 {
+                    let mut y = 21;
+                    if !cells.fits(map, (x, y)) {
+                        continue;
+                    }
+                    while cells.fits(map, (x, y - 1)) {
+                        y -= 1;
+                    }
+                    landed += y;
+                }
+            }
+            black_box(landed)
+        })
+    });
+    group.bench_function("bits", |b| {
+        b.iter(|| {
+            let mut landed = 0;
+            for (_, mask) in pieces.iter() {
+                for x in -3..10 {
+                    let mut y = 21;
+                    if !bits.fits(mask, (x, y)) {
+                        continue;
+                    }
+                    while bits.fits(mask, (x, y - 1)) {
+                        y -= 1;
+                    }
+                    landed += y;
+                }
+            }
+            black_box(landed)
+        })
+    });
+    group.finish();
+}
+
+fn full_rows(c: &mut Criterion) {
+    let (bits, cells) = stack();
+    let mut group = c.benchmark_group("full_rows");
+    group.bench_function("cells", |b| b.iter(|| black_box(cells.full_rows())));
+    group.bench_function("bits", |b| b.iter(|| black_box(bits.full_rows())));
+    group.finish();
+}
+
+criterion_group!(benches, placements, full_rows);
+criterion_main!(benches);
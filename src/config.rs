@@ -0,0 +1,339 @@
+use crate::palette::{Color, Palette};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tetris::gamepad::{Button, GamepadMapping};
+use tetris::input::InputSettings;
+use tetris::{Action, PieceKind, Rules};
+
+// keys are named after glutin's VirtualKeyCode variants, e.g.
+//
+// [keys]
+// move_left = ["Left", "J"]
+// hard_drop = ["Space"]
+//
+// [input]
+// das_ms = 133
+// arr_ms = 0
+//
+// and gamepad buttons after gilrs' Button variants
+//
+// [gamepad]
+// hard_drop = ["DPadUp", "West"]
+// deadzone = 0.3
+//
+// [rules]
+// entry_delay_ms = 100
+// line_clear_delay_ms = 400
+// initial_rotation = true
+//
+// [display]
+// ghost = false
+// palette = "colorblind"
+// colors = { t = [0.6, 0.2, 0.8], garbage = [0.3, 0.3, 0.3] }
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Config {
+    pub keys: KeyBindings,
+    pub input: InputConfig,
+    pub rules: RulesConfig,
+    pub gamepad: GamepadConfig,
+    pub display: DisplayConfig,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub move_left: Vec<String>,
+    pub move_right: Vec<String>,
+    pub rotate_clockwise: Vec<String>,
+    pub rotate_counter_clockwise: Vec<String>,
+    pub rotate_180: Vec<String>,
+    pub soft_drop: Vec<String>,
+    pub hard_drop: Vec<String>,
+    pub hold: Vec<String>,
+    // starts a new game once the current one is over
+    pub restart: Vec<String>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        let keys = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        return KeyBindings {
+            move_left: keys(&["Left"]),
+            move_right: keys(&["Right"]),
+            rotate_clockwise: keys(&["Up", "X"]),
+            rotate_counter_clockwise: keys(&["Z"]),
+            rotate_180: keys(&["A"]),
+            soft_drop: keys(&["Down"]),
+            hard_drop: keys(&["Space"]),
+            hold: keys(&["LShift", "RShift", "C"]),
+            restart: keys(&["Return"]),
+        };
+    }
+}
+
+impl KeyBindings {
+    // the action bound to a key, by its VirtualKeyCode name
+    pub fn action(&self, key: &str) -> Action {
+        let bindings = [
+            (&self.move_left, Action::MoveLeft),
+            (&self.move_right, Action::MoveRight),
+            (&self.rotate_clockwise, Action::RotateClockwise),
+            (
+                &self.rotate_counter_clockwise,
+                Action::RotateCounterClockwise,
+            ),
+            (&self.rotate_180, Action::Rotate180),
+            (&self.soft_drop, Action::SoftDrop),
+            (&self.hard_drop, Action::HardDrop),
+            (&self.hold, Action::Hold),
+        ];
+        for (keys, action) in bindings.iter() {
+            if keys.iter().any(|bound| bound == key) {
+                return *action;
+            }
+        }
+        return Action::None;
+    }
+
+    pub fn is_restart(&self, key: &str) -> bool {
+        return self.restart.iter().any(|bound| bound == key);
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct InputConfig {
+    pub das_ms: u64,
+    pub arr_ms: u64,
+    pub soft_drop_factor: f64,
+    pub das_cut_ms: u64,
+}
+
+impl Default for InputConfig {
+    fn default() -> InputConfig {
+        let settings = InputSettings::default();
+        return InputConfig {
+            das_ms: settings.das.as_millis() as u64,
+            arr_ms: settings.arr.as_millis() as u64,
+            soft_drop_factor: settings.soft_drop_factor,
+            das_cut_ms: settings.das_cut.as_millis() as u64,
+        };
+    }
+}
+
+impl InputConfig {
+    pub fn settings(&self) -> InputSettings {
+        return InputSettings {
+            das: Duration::from_millis(self.das_ms),
+            arr: Duration::from_millis(self.arr_ms),
+            soft_drop_factor: self.soft_drop_factor,
+            das_cut: Duration::from_millis(self.das_cut_ms),
+        };
+    }
+}
+
+// the timing rules that have no command line option
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct RulesConfig {
+    pub entry_delay_ms: u64,
+    pub line_clear_delay_ms: u64,
+    pub initial_rotation: bool,
+    pub initial_hold: bool,
+}
+
+impl Default for RulesConfig {
+    fn default() -> RulesConfig {
+        let rules = Rules::default();
+        return RulesConfig {
+            entry_delay_ms: rules.entry_delay.as_millis() as u64,
+            line_clear_delay_ms: rules.line_clear_delay.as_millis() as u64,
+            initial_rotation: rules.initial_rotation,
+            initial_hold: rules.initial_hold,
+        };
+    }
+}
+
+impl RulesConfig {
+    // the given rules with these in place
+    pub fn apply(&self, rules: Rules) -> Rules {
+        return Rules {
+            entry_delay: Duration::from_millis(self.entry_delay_ms),
+            line_clear_delay: Duration::from_millis(self.line_clear_delay_ms),
+            initial_rotation: self.initial_rotation,
+            initial_hold: self.initial_hold,
+            ..rules
+        };
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct GamepadConfig {
+    pub move_left: Vec<String>,
+    pub move_right: Vec<String>,
+    pub rotate_clockwise: Vec<String>,
+    pub rotate_counter_clockwise: Vec<String>,
+    pub rotate_180: Vec<String>,
+    pub soft_drop: Vec<String>,
+    pub hard_drop: Vec<String>,
+    pub hold: Vec<String>,
+    pub restart: Vec<String>,
+    // how far the left stick has to be pushed before it shifts or soft drops, from 0 to 1
+    pub deadzone: f32,
+}
+
+impl Default for GamepadConfig {
+    fn default() -> GamepadConfig {
+        let mapping = GamepadMapping::default();
+        let buttons = |action: Action| {
+            mapping
+                .buttons
+                .iter()
+                .filter(|(_, bound)| *bound == action)
+                .map(|(button, _)| format!("{:?}", button))
+                .collect()
+        };
+        return GamepadConfig {
+            move_left: buttons(Action::MoveLeft),
+            move_right: buttons(Action::MoveRight),
+            rotate_clockwise: buttons(Action::RotateClockwise),
+            rotate_counter_clockwise: buttons(Action::RotateCounterClockwise),
+            rotate_180: buttons(Action::Rotate180),
+            soft_drop: buttons(Action::SoftDrop),
+            hard_drop: buttons(Action::HardDrop),
+            hold: buttons(Action::Hold),
+            restart: mapping
+                .restart
+                .iter()
+                .map(|button| format!("{:?}", button))
+                .collect(),
+            deadzone: mapping.deadzone,
+        };
+    }
+}
+
+impl GamepadConfig {
+    pub fn mapping(&self) -> GamepadMapping {
+        let bindings = [
+            (&self.move_left, Action::MoveLeft),
+            (&self.move_right, Action::MoveRight),
+            (&self.rotate_clockwise, Action::RotateClockwise),
+            (
+                &self.rotate_counter_clockwise,
+                Action::RotateCounterClockwise,
+            ),
+            (&self.rotate_180, Action::Rotate180),
+            (&self.soft_drop, Action::SoftDrop),
+            (&self.hard_drop, Action::HardDrop),
+            (&self.hold, Action::Hold),
+        ];
+        let mut buttons = Vec::new();
+        for (names, action) in bindings.iter() {
+            for button in names.iter().filter_map(|name| button_from_name(name)) {
+                buttons.push((button, *action));
+            }
+        }
+        return GamepadMapping {
+            buttons,
+            restart: self
+                .restart
+                .iter()
+                .filter_map(|name| button_from_name(name))
+                .collect(),
+            deadzone: self.deadzone,
+        };
+    }
+}
+
+fn button_from_name(name: &str) -> Option<Button> {
+    let button = Button::from_name(name);
+    if button.is_none() {
+        eprintln!("unknown gamepad button {}", name);
+    }
+    return button;
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    // shows where the falling piece would land
+    pub ghost: bool,
+    // "standard" or "colorblind"
+    pub palette: String,
+    // colours replacing the palette's, by lowercase piece kind or "background"
+    pub colors: HashMap<String, [f32; 3]>,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> DisplayConfig {
+        return DisplayConfig {
+            ghost: true,
+            palette: "standard".to_string(),
+            colors: HashMap::new(),
+        };
+    }
+}
+
+impl DisplayConfig {
+    // the chosen palette with the colours of the pieces being played with, then the colours
+    // set here, on top
+    pub fn palette(&self, piece_colors: &[(PieceKind, Color)]) -> Palette {
+        let mut palette = Palette::from_name(&self.palette).unwrap_or_else(|| {
+            eprintln!("unknown palette {}", self.palette);
+            Palette::standard()
+        });
+        for &(kind, color) in piece_colors.iter() {
+            palette.set_color(kind, color);
+        }
+        for (name, &[r, g, b]) in self.colors.iter() {
+            let color = match name.to_lowercase().as_str() {
+                "i" => &mut palette.i,
+                "o" => &mut palette.o,
+                "t" => &mut palette.t,
+                "s" => &mut palette.s,
+                "z" => &mut palette.z,
+                "j" => &mut palette.j,
+                "l" => &mut palette.l,
+                "garbage" => &mut palette.garbage,
+                "background" => &mut palette.background,
+                _ => {
+                    eprintln!("unknown colour {}", name);
+                    continue;
+                }
+            };
+            *color = (r, g, b);
+        }
+        return palette;
+    }
+}
+
+// ~/.config/tetris/config.toml on Linux, or wherever the platform keeps configuration
+pub fn default_path() -> Option<PathBuf> {
+    return dirs::config_dir().map(|dir| dir.join("tetris").join("config.toml"));
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| format!("couldn't read {}: {}", path.display(), error))?;
+        return toml::from_str(&text)
+            .map_err(|error| format!("couldn't parse {}: {}", path.display(), error));
+    }
+
+    // the config at the given path, or at the default location when none is given, falling back
+    // to the defaults if there's nothing to load
+    pub fn load_or_default(path: Option<PathBuf>) -> Config {
+        let path = path.or_else(|| default_path().filter(|path| path.exists()));
+        match path {
+            Some(path) => Config::load(&path).unwrap_or_else(|error| {
+                eprintln!("{}", error);
+                Config::default()
+            }),
+            None => Config::default(),
+        }
+    }
+}
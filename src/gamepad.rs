@@ -0,0 +1,64 @@
+use tetris::gamepad::{Axis, Button, GamepadEvent, GamepadSource};
+
+// every controller gilrs can see, all of them drive the same game
+pub struct GilrsSource {
+    gilrs: gilrs::Gilrs,
+}
+
+impl GilrsSource {
+    pub fn new() -> Result<GilrsSource, String> {
+        return match gilrs::Gilrs::new() {
+            Ok(gilrs) => Ok(GilrsSource { gilrs }),
+            Err(error) => Err(format!("couldn't open gamepads: {}", error)),
+        };
+    }
+}
+
+impl GamepadSource for GilrsSource {
+    fn poll(&mut self) -> Option<GamepadEvent> {
+        // skip over events with no counterpart until one turns up or there are none left
+        while let Some(event) = self.gilrs.next_event() {
+            let translated = match event.event {
+                gilrs::EventType::ButtonPressed(button, _) => {
+                    translate_button(button).map(GamepadEvent::Pressed)
+                }
+                gilrs::EventType::ButtonReleased(button, _) => {
+                    translate_button(button).map(GamepadEvent::Released)
+                }
+                gilrs::EventType::AxisChanged(gilrs::Axis::LeftStickX, value, _) => {
+                    Some(GamepadEvent::AxisChanged(Axis::LeftStickX, value))
+                }
+                gilrs::EventType::AxisChanged(gilrs::Axis::LeftStickY, value, _) => {
+                    Some(GamepadEvent::AxisChanged(Axis::LeftStickY, value))
+                }
+                _ => None,
+            };
+            if translated.is_some() {
+                return translated;
+            }
+        }
+        return None;
+    }
+}
+
+fn translate_button(button: gilrs::Button) -> Option<Button> {
+    match button {
+        gilrs::Button::South => Some(Button::South),
+        gilrs::Button::East => Some(Button::East),
+        gilrs::Button::North => Some(Button::North),
+        gilrs::Button::West => Some(Button::West),
+        gilrs::Button::LeftTrigger => Some(Button::LeftTrigger),
+        gilrs::Button::LeftTrigger2 => Some(Button::LeftTrigger2),
+        gilrs::Button::RightTrigger => Some(Button::RightTrigger),
+        gilrs::Button::RightTrigger2 => Some(Button::RightTrigger2),
+        gilrs::Button::Select => Some(Button::Select),
+        gilrs::Button::Start => Some(Button::Start),
+        gilrs::Button::LeftThumb => Some(Button::LeftThumb),
+        gilrs::Button::RightThumb => Some(Button::RightThumb),
+        gilrs::Button::DPadUp => Some(Button::DPadUp),
+        gilrs::Button::DPadDown => Some(Button::DPadDown),
+        gilrs::Button::DPadLeft => Some(Button::DPadLeft),
+        gilrs::Button::DPadRight => Some(Button::DPadRight),
+        _ => None,
+    }
+}
@@ -0,0 +1,4 @@
+// the headless game engine, the glium frontend in main.rs is built on top of this
+mod tetris;
+
+pub use crate::tetris::*;
@@ -3,12 +3,21 @@ extern crate glium;
 extern crate image;
 extern crate rust_lm;
 
-mod tetris;
+mod config;
+mod gamepad;
+mod palette;
+mod scores;
 
-use crate::tetris::PlaySpace;
+use crate::config::Config;
+use crate::gamepad::GilrsSource;
+use crate::scores::HighScores;
 use glium::backend::glutin::glutin::event::DeviceEvent;
 use glium::{glutin, Surface};
 use rust_lm::Mat4;
+use tetris::gamepad::GamepadInput;
+use tetris::input::InputHandler;
+use tetris::marathon::{Marathon, MarathonGoal};
+use tetris::PlaySpace;
 
 #[derive(Copy, Clone)]
 struct Vertex {
@@ -44,8 +53,9 @@ fn main() {
         #version 140
         in vec3 my_color;
         out vec4 color;
+        uniform float alpha;
         void main() {
-            color = vec4(my_color, 1.0);
+            color = vec4(my_color, alpha);
         }
     "#;
 
@@ -53,31 +63,141 @@ fn main() {
         glium::Program::from_source(&display, vertex_shader_src, fragment_shader_src, None)
             .unwrap();
 
-    let mut main_play_space = PlaySpace::initialize();
+    let mut rules = tetris::Rules::default();
+    let mut seed: Option<u64> = None;
+    let mut config_path = None;
+    let mut pieces = None;
+    let mut marathon = Marathon::default();
+    let args: Vec<String> = std::env::args().collect();
+    for i in 0..args.len() {
+        if args[i] == "--rotation" && i + 1 < args.len() {
+            match tetris::rotation::RotationSystemKind::from_name(&args[i + 1]) {
+                Some(rotation_system) => rules.rotation_system = rotation_system,
+                None => eprintln!("unknown rotation system {}", args[i + 1]),
+            }
+        }
+        if args[i] == "--randomizer" && i + 1 < args.len() {
+            match tetris::randomizer::RandomizerKind::from_name(&args[i + 1]) {
+                Some(randomizer) => rules.randomizer = randomizer,
+                None => eprintln!("unknown randomizer {}", args[i + 1]),
+            }
+        }
+        if args[i] == "--seed" && i + 1 < args.len() {
+            match args[i + 1].parse() {
+                Ok(value) => seed = Some(value),
+                Err(_) => eprintln!("invalid seed {}", args[i + 1]),
+            }
+        }
+        if args[i] == "--config" && i + 1 < args.len() {
+            config_path = Some(std::path::PathBuf::from(&args[i + 1]));
+        }
+        if args[i] == "--width" && i + 1 < args.len() {
+            match args[i + 1].parse() {
+                Ok(value) => rules.width = value,
+                Err(_) => eprintln!("invalid width {}", args[i + 1]),
+            }
+        }
+        if args[i] == "--height" && i + 1 < args.len() {
+            match args[i + 1].parse() {
+                Ok(value) => rules.visible_height = value,
+                Err(_) => eprintln!("invalid height {}", args[i + 1]),
+            }
+        }
+        if args[i] == "--buffer" && i + 1 < args.len() {
+            match args[i + 1].parse() {
+                Ok(value) => rules.buffer_height = value,
+                Err(_) => eprintln!("invalid buffer height {}", args[i + 1]),
+            }
+        }
+        if args[i] == "--pieces" && i + 1 < args.len() {
+            match tetris::piece_file::load(&args[i + 1]) {
+                Ok(loaded) => pieces = Some(loaded),
+                Err(error) => eprintln!("{}", error),
+            }
+        }
+        if args[i] == "--level" && i + 1 < args.len() {
+            match args[i + 1].parse() {
+                Ok(value) if value >= 1 => marathon.start_level = value,
+                _ => eprintln!("invalid level {}", args[i + 1]),
+            }
+        }
+        if args[i] == "--goal" && i + 1 < args.len() {
+            match MarathonGoal::from_name(&args[i + 1]) {
+                Some(goal) => marathon.goal = goal,
+                None => eprintln!("unknown goal {}", args[i + 1]),
+            }
+        }
+        if args[i] == "--preview" && i + 1 < args.len() {
+            match args[i + 1].parse() {
+                Ok(value) => rules.preview = value,
+                Err(_) => eprintln!("invalid preview length {}", args[i + 1]),
+            }
+        }
+    }
+
+    let config = Config::load_or_default(config_path);
+    let rules = marathon.rules(config.rules.apply(rules));
+    let seed = seed.unwrap_or_else(rand::random);
+    let (mut main_play_space, piece_colors) = match pieces {
+        Some(pieces) => (
+            PlaySpace::initialize_with_piece_set(rules, pieces.set, seed),
+            pieces
+                .colors
+                .iter()
+                .map(|&(kind, [r, g, b])| (kind, (r, g, b)))
+                .collect(),
+        ),
+        None => (
+            PlaySpace::initialize_with_rules_and_seed(rules, seed),
+            Vec::new(),
+        ),
+    };
+    println!("seed {}", main_play_space.seed());
+    let palette = config.display.palette(&piece_colors);
+    let mut input_handler = InputHandler::new(config.input.settings());
+    let board = main_play_space.board();
+    input_handler.set_board_size(board.width(), board.height());
+    let mut gamepad_input = GamepadInput::new(config.gamepad.mapping());
+    let scores_path = scores::default_path();
+    let mut high_scores = match &scores_path {
+        Some(path) => HighScores::load_or_default(path),
+        None => HighScores::default(),
+    };
+    let mut gamepads = GilrsSource::new()
+        .map_err(|error| eprintln!("{}", error))
+        .ok();
+    let mut last_tick = std::time::Instant::now();
+    // what the last lock earned, shown after the score until the next one, the events come a
+    // whole entry delay before the next piece spawns
+    let mut bonuses: Vec<String> = Vec::new();
 
     // Real(tm) render loop
     events_loop.run(move |event, _, control_flow| {
-        let mut currentAction = tetris::Action::None;
         match event {
             glutin::event::Event::WindowEvent { event, .. } => match event {
                 glutin::event::WindowEvent::CloseRequested => {
                     *control_flow = glutin::event_loop::ControlFlow::Exit;
                     return;
                 }
-                glutin::event::WindowEvent::KeyboardInput { input, .. } => match input.state {
-                    glutin::event::ElementState::Pressed => {
-                        if let 105 = input.scancode {
-                            currentAction = tetris::Action::MoveLeft;
+                glutin::event::WindowEvent::KeyboardInput { input, .. } => {
+                    let key = match input.virtual_keycode {
+                        Some(keycode) => format!("{:?}", keycode),
+                        None => return,
+                    };
+                    let action = config.keys.action(&key);
+                    match input.state {
+                        glutin::event::ElementState::Pressed => {
+                            input_handler.press(action);
+                            if config.keys.is_restart(&key) {
+                                if let tetris::GameState::GameOver { .. } = main_play_space.state()
+                                {
+                                    main_play_space.restart();
+                                }
+                            }
                         }
-                        if let 106 = input.scancode {
-                            currentAction = tetris::Action::MoveRight;
-                        }
-                        println!("{}", input.scancode);
+                        glutin::event::ElementState::Released => input_handler.release(action),
                     }
-                    glutin::event::ElementState::Released => match input.scancode {
-                        _ => {}
-                    },
-                },
+                }
                 _ => return,
             },
             glutin::event::Event::NewEvents(cause) => match cause {
@@ -92,24 +212,111 @@ fn main() {
             std::time::Instant::now() + std::time::Duration::from_nanos(16_666_667);
         *control_flow = glutin::event_loop::ControlFlow::WaitUntil(next_frame_time);
 
-        main_play_space.tick(currentAction);
+        let now = std::time::Instant::now();
+        let elapsed = now - last_tick;
+        last_tick = now;
+        if let Some(gamepads) = gamepads.as_mut() {
+            if gamepad_input.poll(gamepads, &mut input_handler) {
+                if let tetris::GameState::GameOver { .. } = main_play_space.state() {
+                    main_play_space.restart();
+                }
+            }
+        }
+        for action in input_handler.update(elapsed, main_play_space.gravity()) {
+            main_play_space.tick(action, std::time::Duration::from_secs(0));
+        }
+        main_play_space.tick(tetris::Action::None, elapsed);
+
+        let mut title = None;
+        for event in main_play_space.drain_events() {
+            match event {
+                tetris::event::GameEvent::Locked { .. } => bonuses.clear(),
+                tetris::event::GameEvent::TSpin { kind, lines } => {
+                    bonuses.push(format!("T-spin {:?} {}", kind, lines));
+                }
+                tetris::event::GameEvent::BackToBack { chain } => {
+                    bonuses.push(format!("Back-to-back x{}", chain));
+                }
+                tetris::event::GameEvent::Combo { combo } => {
+                    bonuses.push(format!("Combo {}", combo));
+                }
+                tetris::event::GameEvent::PerfectClear { .. } => {
+                    bonuses.push("Perfect clear".to_string());
+                }
+                tetris::event::GameEvent::PieceSpawned { .. } => {
+                    input_handler.piece_spawned();
+                    title = Some(hud(&main_play_space, &marathon, &bonuses.join("  ")));
+                }
+                tetris::event::GameEvent::GameOver { reason } => {
+                    bonuses.clear();
+                    let score = scores::Score {
+                        score: main_play_space.score(),
+                        lines: main_play_space.lines_cleared(),
+                        level: main_play_space.level(),
+                        start_level: marathon.start_level,
+                    };
+                    let place = high_scores.record(marathon.goal.name(), score);
+                    if let Some(path) = &scores_path {
+                        if let Err(error) = high_scores.save(path) {
+                            eprintln!("{}", error);
+                        }
+                    }
+                    println!("marathon {} high scores", marathon.goal.name());
+                    for (n, entry) in high_scores.table(marathon.goal.name()).iter().enumerate() {
+                        println!(
+                            "{:>2}. {:>8}  lines {:>3}  level {:>2}  from level {}",
+                            n + 1,
+                            entry.score,
+                            entry.lines,
+                            entry.level,
+                            entry.start_level
+                        );
+                    }
+                    let ending = match reason {
+                        tetris::GameOverReason::GoalReached => "Marathon complete".to_string(),
+                        _ => format!("Game over ({:?})", reason),
+                    };
+                    let place = match place {
+                        Some(place) => format!(", #{} in the high scores", place),
+                        None => String::new(),
+                    };
+                    title = Some(format!(
+                        "{}, score {}{}, press Enter to restart",
+                        ending,
+                        main_play_space.score(),
+                        place
+                    ));
+                }
+                _ => {}
+            }
+        }
+        if let Some(title) = title {
+            display.gl_window().window().set_title(&title);
+        }
+        let game_over = main_play_space.state() != tetris::GameState::Playing;
 
         let mut target = display.draw();
         target.clear_color(0.0, 0.0, 0.0, 1.0);
 
+        // fit the main space in the middle of the window, at most half as wide as it, with one
+        // unit per block in the field matrix and the bottom left block centred on the origin
+        let width = main_play_space.board().width();
+        let visible_height = main_play_space.rules().visible_height;
+        let block = (1.0 / width as f32).min(2.0 / visible_height as f32);
+        let left = -block * width as f32 / 2.0;
+        let bottom = -block * visible_height as f32 / 2.0;
+        let field = Mat4::identity()
+            .scale_by(block, block, 1.0)
+            .translate_by(left + block / 2.0, bottom + block / 2.0, 0.0)
+            .matrix;
+
         // draw main space
-        let points = vec![
-            [-0.5f32, 1.0],
-            [-0.5, -1.0],
-            [0.5, 1.0],
-            [-0.5, -1.0],
-            [0.5, 1.0],
-            [0.5, -1.0],
-        ];
-        let points_proper = points_to_points_proper(points, main_play_space.color);
+        let points = rectangle([left, -bottom], [-left, bottom]);
+        let points_proper = points_to_points_proper(points, palette.background);
 
         let uniforms = uniform! {
             matrix: Mat4::identity().matrix,
+            alpha: 1.0f32,
         };
 
         let vertex_buffer = glium::VertexBuffer::new(&display, &points_proper).unwrap();
@@ -124,28 +331,108 @@ fn main() {
             )
             .unwrap();
 
-        // draw settled blocks
-        let mut vertices: Vec<Vertex> = Vec::new();
-        let simple_space = main_play_space.space_with_falling_as_settled();
-        for i in 0..simple_space.len() {
-            for j in 0..simple_space[i].len() {
-                if let tetris::SpaceState::SettledTetromino(color) = simple_space[i][j] {
-                    let points = vec![
-                        [i as f32 - 0.5, j as f32 - 0.5],
-                        [i as f32 - 0.5, j as f32 + 0.5],
-                        [i as f32 + 0.5, j as f32 + 0.5],
-                        [i as f32 - 0.5, j as f32 - 0.5],
-                        [i as f32 + 0.5, j as f32 + 0.5],
-                        [i as f32 + 0.5, j as f32 - 0.5],
-                    ];
-                    let mut points_proper = points_to_points_proper(points, color);
-                    vertices.append(&mut points_proper);
+        // draw the ghost under the falling piece, blended so the main space shows through
+        if config.display.ghost && !game_over && main_play_space.piece_active() {
+            let ghost = main_play_space.ghost_position();
+            let tetromino = main_play_space.current_tetromino();
+            let map = tetromino.map(main_play_space.current_tetromino_rotation());
+            let color = palette.color(tetromino.kind());
+            let mut vertices: Vec<Vertex> = Vec::new();
+            for (i, row) in map.iter().enumerate() {
+                for (j, cell) in row.iter().enumerate() {
+                    let y = ghost.1 - i as i32;
+                    if let tetris::SpaceState::FallingTetromino = cell {
+                        if y >= visible_height as i32 {
+                            continue;
+                        }
+                        let x = (ghost.0 + j as i32) as f32;
+                        let y = y as f32;
+                        let points = rectangle([x - 0.5, y + 0.5], [x + 0.5, y - 0.5]);
+                        vertices.append(&mut points_to_points_proper(points, color));
+                    }
                 }
             }
+            let vertex_buffer = glium::VertexBuffer::new(&display, &vertices).unwrap();
+            let uniforms = uniform! {
+                matrix: field,
+                alpha: 0.3f32,
+            };
+            let parameters = glium::DrawParameters {
+                blend: glium::Blend::alpha_blending(),
+                ..Default::default()
+            };
+            target
+                .draw(
+                    &vertex_buffer,
+                    &index_buffer,
+                    &program,
+                    &uniforms,
+                    &parameters,
+                )
+                .unwrap();
+        }
+
+        // draw settled blocks and the falling tetromino, leaving out the buffer rows
+        let mut blocks = Vec::new();
+        let board = main_play_space.board();
+        for i in 0..width {
+            for j in 0..visible_height {
+                if let tetris::SpaceState::SettledTetromino(kind) = board.cell(i, j) {
+                    let mut color = palette.color(kind);
+                    if game_over {
+                        color = palette.game_over;
+                    } else if main_play_space.clearing_rows().contains(&(j as i32)) {
+                        // flash the rows waiting out the line clear delay
+                        color = palette.clearing;
+                    }
+                    blocks.push((i as f32, j as f32, color));
+                }
+            }
+        }
+        for (i, j) in main_play_space.falling_blocks() {
+            if j >= visible_height as i32 {
+                continue;
+            }
+            let color = palette.color(main_play_space.current_tetromino().kind());
+            blocks.push((i as f32, j as f32, color));
+        }
+        let mut vertices: Vec<Vertex> = Vec::new();
+        for (i, j, color) in blocks {
+            let points = vec![
+                [i - 0.5, j - 0.5],
+                [i - 0.5, j + 0.5],
+                [i + 0.5, j + 0.5],
+                [i - 0.5, j - 0.5],
+                [i + 0.5, j + 0.5],
+                [i + 0.5, j - 0.5],
+            ];
+            let mut points_proper = points_to_points_proper(points, color);
+            vertices.append(&mut points_proper);
         }
         let vertex_buffer = glium::VertexBuffer::new(&display, &vertices).unwrap();
         let uniforms = uniform! {
-            matrix: Mat4::identity().scale_by(0.1, 0.1, 1.0).translate_by(-0.45, -0.95, 0.0).matrix,
+            matrix: field,
+            alpha: 1.0f32,
+        };
+        target
+            .draw(
+                &vertex_buffer,
+                &index_buffer,
+                &program,
+                &uniforms,
+                &Default::default(),
+            )
+            .unwrap();
+
+        // draw held piece to the left of the main space
+        let held = main_play_space
+            .held()
+            .map(|kind| (main_play_space.piece_map(kind), palette.color(kind)));
+        let vertices = piece_box_vertices(held, [-0.95, 0.9], 0.4);
+        let vertex_buffer = glium::VertexBuffer::new(&display, &vertices).unwrap();
+        let uniforms = uniform! {
+            matrix: Mat4::identity().matrix,
+            alpha: 1.0f32,
         };
         target
             .draw(
@@ -157,10 +444,44 @@ fn main() {
             )
             .unwrap();
 
+        // draw the next queue to the right of the main space
+        let mut vertices: Vec<Vertex> = Vec::new();
+        for (n, kind) in main_play_space.next_queue().iter().enumerate() {
+            let piece = Some((main_play_space.piece_map(*kind), palette.color(*kind)));
+            let top_left = [0.6, 0.9 - n as f32 * 0.3];
+            vertices.append(&mut piece_box_vertices(piece, top_left, 0.3));
+        }
+        let vertex_buffer = glium::VertexBuffer::new(&display, &vertices).unwrap();
+        target
+            .draw(
+                &vertex_buffer,
+                &index_buffer,
+                &program,
+                &uniforms,
+                &Default::default(),
+            )
+            .unwrap();
+
         target.finish().unwrap();
     });
 }
 
+// the window title, which stands in for a heads up display
+fn hud(play_space: &PlaySpace, marathon: &Marathon, bonuses: &str) -> String {
+    let lines = match marathon.goal.lines() {
+        Some(goal) => format!("{}/{}", play_space.lines_cleared(), goal),
+        None => play_space.lines_cleared().to_string(),
+    };
+    return format!(
+        "Marathon {}  Score {}  Level {}  Lines {}  {}",
+        marathon.goal.name(),
+        play_space.score(),
+        play_space.level(),
+        lines,
+        bonuses
+    );
+}
+
 fn points_to_points_proper(points: Vec<[f32; 2]>, color: (f32, f32, f32)) -> Vec<Vertex> {
     let mut points_proper: Vec<Vertex> = Vec::new();
     for point in points {
@@ -171,3 +492,39 @@ fn points_to_points_proper(points: Vec<[f32; 2]>, color: (f32, f32, f32)) -> Vec
     }
     return points_proper;
 }
+
+// a square box of blocks size across with its top left corner at top_left, showing an optional
+// piece
+fn piece_box_vertices(
+    piece: Option<(tetris::PieceMap, (f32, f32, f32))>,
+    top_left: [f32; 2],
+    size: f32,
+) -> Vec<Vertex> {
+    let block = size / tetris::BOX as f32;
+    let bottom_right = [top_left[0] + size, top_left[1] - size];
+    let mut vertices = points_to_points_proper(rectangle(top_left, bottom_right), (0.2, 0.2, 0.2));
+    if let Some((map, color)) = piece {
+        for (i, row) in map.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                if let tetris::SpaceState::FallingTetromino = cell {
+                    let left = top_left[0] + j as f32 * block;
+                    let top = top_left[1] - i as f32 * block;
+                    let points = rectangle([left, top], [left + block, top - block]);
+                    vertices.append(&mut points_to_points_proper(points, color));
+                }
+            }
+        }
+    }
+    return vertices;
+}
+
+fn rectangle(corner: [f32; 2], opposite: [f32; 2]) -> Vec<[f32; 2]> {
+    return vec![
+        [corner[0], corner[1]],
+        [corner[0], opposite[1]],
+        [opposite[0], opposite[1]],
+        [corner[0], corner[1]],
+        [opposite[0], opposite[1]],
+        [opposite[0], corner[1]],
+    ];
+}
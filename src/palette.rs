@@ -0,0 +1,106 @@
+use tetris::PieceKind;
+
+pub type Color = (f32, f32, f32);
+
+// the colours blocks are drawn in, the engine only knows what kind each block is
+#[derive(Clone, Debug)]
+pub struct Palette {
+    pub i: Color,
+    pub o: Color,
+    pub t: Color,
+    pub s: Color,
+    pub z: Color,
+    pub j: Color,
+    pub l: Color,
+    pub garbage: Color,
+    // pieces from a piece set that aren't tetrominoes, by number
+    pub custom: Vec<Color>,
+    // behind the blocks of the main space
+    pub background: Color,
+    // every settled block once the game is over
+    pub game_over: Color,
+    // rows waiting out the line clear delay
+    pub clearing: Color,
+}
+
+impl Palette {
+    // the guideline colours
+    pub fn standard() -> Palette {
+        return Palette {
+            i: (0.0, 1.0, 1.0),
+            o: (1.0, 1.0, 0.0),
+            t: (0.5, 0.0, 0.5),
+            s: (0.0, 1.0, 0.0),
+            z: (1.0, 0.0, 0.0),
+            j: (0.0, 0.0, 1.0),
+            l: (1.0, 0.5, 0.0),
+            garbage: (0.5, 0.5, 0.5),
+            custom: Vec::new(),
+            background: (1.0, 1.0, 1.0),
+            game_over: (0.4, 0.4, 0.4),
+            clearing: (1.0, 1.0, 1.0),
+        };
+    }
+
+    // the Okabe-Ito colours, told apart with any of the common kinds of colour blindness
+    pub fn colorblind() -> Palette {
+        return Palette {
+            i: (0.34, 0.71, 0.91),
+            o: (0.94, 0.89, 0.26),
+            t: (0.8, 0.47, 0.65),
+            s: (0.0, 0.62, 0.45),
+            z: (0.84, 0.37, 0.0),
+            j: (0.0, 0.45, 0.7),
+            l: (0.9, 0.62, 0.0),
+            garbage: (0.6, 0.6, 0.6),
+            custom: Vec::new(),
+            background: (1.0, 1.0, 1.0),
+            game_over: (0.4, 0.4, 0.4),
+            clearing: (0.0, 0.0, 0.0),
+        };
+    }
+
+    pub fn from_name(name: &str) -> Option<Palette> {
+        match name.to_lowercase().as_str() {
+            "standard" => Some(Palette::standard()),
+            "colorblind" => Some(Palette::colorblind()),
+            _ => None,
+        }
+    }
+
+    pub fn color(&self, kind: PieceKind) -> Color {
+        match kind {
+            PieceKind::I => self.i,
+            PieceKind::O => self.o,
+            PieceKind::T => self.t,
+            PieceKind::S => self.s,
+            PieceKind::Z => self.z,
+            PieceKind::J => self.j,
+            PieceKind::L => self.l,
+            PieceKind::Garbage => self.garbage,
+            PieceKind::Custom(n) => match self.custom.get(n as usize) {
+                Some(&color) => color,
+                None => self.garbage,
+            },
+        }
+    }
+
+    pub fn set_color(&mut self, kind: PieceKind, color: Color) {
+        match kind {
+            PieceKind::I => self.i = color,
+            PieceKind::O => self.o = color,
+            PieceKind::T => self.t = color,
+            PieceKind::S => self.s = color,
+            PieceKind::Z => self.z = color,
+            PieceKind::J => self.j = color,
+            PieceKind::L => self.l = color,
+            PieceKind::Garbage => self.garbage = color,
+            PieceKind::Custom(n) => {
+                if self.custom.len() <= n as usize {
+                    self.custom.resize(n as usize + 1, self.garbage);
+                }
+                self.custom[n as usize] = color;
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// how many results each table keeps
+const TABLE_SIZE: usize = 10;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Score {
+    pub score: i32,
+    pub lines: i32,
+    pub level: i32,
+    pub start_level: i32,
+}
+
+// the best results of each marathon goal, kept between runs as e.g.
+//
+// [[marathon.150]]
+// score = 48200
+// lines = 150
+// level = 16
+// start_level = 1
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct HighScores {
+    marathon: BTreeMap<String, Vec<Score>>,
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    return dirs::data_dir().map(|dir| dir.join("tetris").join("scores.toml"));
+}
+
+impl HighScores {
+    pub fn load(path: &Path) -> Result<HighScores, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| format!("couldn't read {}: {}", path.display(), error))?;
+        return toml::from_str(&text)
+            .map_err(|error| format!("couldn't parse {}: {}", path.display(), error));
+    }
+
+    // the scores at the given path, empty if nothing has been saved there yet
+    pub fn load_or_default(path: &Path) -> HighScores {
+        if !path.exists() {
+            return HighScores::default();
+        }
+        return HighScores::load(path).unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            HighScores::default()
+        });
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|error| format!("couldn't create {}: {}", dir.display(), error))?;
+        }
+        let text = toml::to_string(self).map_err(|error| error.to_string())?;
+        return std::fs::write(path, text)
+            .map_err(|error| format!("couldn't write {}: {}", path.display(), error));
+    }
+
+    // best first
+    pub fn table(&self, goal: &str) -> &[Score] {
+        match self.marathon.get(goal) {
+            Some(table) => table,
+            None => &[],
+        }
+    }
+
+    // adds a result to a goal's table, returning its place from 1 if it's good enough to stay
+    pub fn record(&mut self, goal: &str, score: Score) -> Option<usize> {
+        let table = self.marathon.entry(goal.to_string()).or_default();
+        // after any equal scores, so an earlier result keeps its place
+        let place = table
+            .iter()
+            .position(|entry| entry.score < score.score)
+            .unwrap_or(table.len());
+        if place >= TABLE_SIZE {
+            return None;
+        }
+        table.insert(place, score);
+        table.truncate(TABLE_SIZE);
+        return Some(place + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // results told apart by their lines
+    fn result(score: i32, lines: i32) -> Score {
+        return Score {
+            score,
+            lines,
+            level: 1,
+            start_level: 1,
+        };
+    }
+
+    fn lines(table: &[Score]) -> Vec<i32> {
+        return table.iter().map(|score| score.lines).collect();
+    }
+
+    #[test]
+    fn record_keeps_the_best_first() {
+        let mut scores = HighScores::default();
+        assert_eq!(scores.record("150", result(100, 1)), Some(1));
+        assert_eq!(scores.record("150", result(300, 2)), Some(1));
+        assert_eq!(scores.record("150", result(200, 3)), Some(2));
+        // after the earlier equal score
+        assert_eq!(scores.record("150", result(200, 4)), Some(3));
+        assert_eq!(lines(scores.table("150")), vec![2, 3, 4, 1]);
+        assert!(scores.table("200").is_empty());
+    }
+
+    #[test]
+    fn record_drops_results_past_the_table() {
+        let mut scores = HighScores::default();
+        for lines in 0..TABLE_SIZE as i32 {
+            scores.record("endless", result(1000, lines));
+        }
+        assert_eq!(scores.record("endless", result(1000, 10)), None);
+        assert_eq!(scores.record("endless", result(999, 11)), None);
+        assert_eq!(scores.record("endless", result(1001, 12)), Some(1));
+        let table = scores.table("endless");
+        assert_eq!(table.len(), TABLE_SIZE);
+        assert_eq!(lines(table), vec![12, 0, 1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+}
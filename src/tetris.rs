@@ -1,1001 +1,1380 @@
-use rand::random;
+pub mod board;
+pub mod event;
+pub mod gamepad;
+pub mod input;
+pub mod marathon;
+#[cfg(feature = "serde")]
+pub mod piece_file;
+pub mod piece_set;
+pub mod randomizer;
+pub mod rotation;
 
-#[derive(Copy, Clone)]
+use crate::tetris::board::{Board, PieceMask, MAX_WIDTH};
+use crate::tetris::event::{ClearKind, GameEvent, TSpinKind};
+use crate::tetris::piece_set::PieceSet;
+use crate::tetris::randomizer::{Randomizer, RandomizerKind};
+use crate::tetris::rotation::{RotationSystem, RotationSystemKind};
+use rand::SeedableRng;
+use rand_pcg::Pcg64;
+use std::time::Duration;
+
+// pieces are drawn in a square box of this many rows and columns, big enough for pentominoes
+pub const BOX: usize = 5;
+
+// a piece's blocks in one rotation, indexed [row][column] from the top left of its box
+pub type PieceMap = [[SpaceState; BOX]; BOX];
+
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum SpaceState {
     Empty,
     FallingTetromino,
-    SettledTetromino((f32, f32, f32)),
+    SettledTetromino(PieceKind),
 }
 
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum Action {
     None,
     MoveLeft,
     MoveRight,
     RotateClockwise,
     RotateCounterClockwise,
+    Rotate180,
+    SoftDrop,
+    HardDrop,
+    Hold,
+}
+
+// what a block is made of, the renderer picks its colour
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum PieceKind {
+    I,
+    O,
+    T,
+    S,
+    Z,
+    J,
+    L,
+    // blocks that were never part of a piece, they're only ever settled
+    Garbage,
+    // a piece from a loaded PieceSet that isn't one of the seven tetrominoes, by its index in
+    // the set
+    Custom(u8),
+}
+
+pub const MAX_PREVIEW: usize = 6;
+
+// the simulation advances in fixed steps of one 60 fps frame, whatever rate tick is called at
+pub const FRAME: Duration = Duration::from_nanos(16_666_667);
+// elapsed time beyond this is dropped rather than simulated, e.g. after the window was suspended
+const MAX_CATCH_UP: Duration = Duration::from_millis(250);
+// moves and rotations on the ground that restart the lock delay under extended placement
+pub const MAX_LOCK_RESETS: u32 = 15;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LockReset {
+    // moving or rotating restarts the lock delay, up to MAX_LOCK_RESETS times per row reached
+    ExtendedPlacement,
+    // moving or rotating always restarts the lock delay
+    Infinite,
+    // only falling to a new lowest row restarts the lock delay
+    StepReset,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Gravity {
+    // (0.8 - (level - 1) * 0.007)^(level - 1) seconds per row
+    Guideline,
+    // rows per frame regardless of level, 20.0 drops pieces straight to the stack
+    Fixed(f64),
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GameOverReason {
+    // a new piece spawned overlapping settled blocks
+    BlockOut,
+    // a piece locked entirely above the visible rows
+    LockOut,
+    // a piece locked partly above the visible rows, when Rules::partial_lock_out is set
+    PartialLockOut,
+    // Rules::line_goal lines were cleared, the game was won
+    GoalReached,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GameState {
+    Playing,
+    GameOver { reason: GameOverReason },
+}
+
+// what the play space is doing between one piece and the next
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Phase {
+    // the entry delay (ARE) before the next piece appears
+    Spawning,
+    // the piece is in the air
+    Falling,
+    // the piece is resting on the stack, counting down its lock delay
+    Locking,
+    // full rows are waiting to be removed, see PlaySpace::clearing_rows
+    LineClearing,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Rules {
+    pub rotation_system: RotationSystemKind,
+    pub randomizer: RandomizerKind,
+    // how many upcoming pieces are shown, up to MAX_PREVIEW
+    pub preview: usize,
+    pub partial_lock_out: bool,
+    // how long a piece rests on the stack before locking
+    pub lock_delay: Duration,
+    pub lock_reset: LockReset,
+    pub gravity: Gravity,
+    // ARE, the wait between a piece locking and the next one spawning
+    pub entry_delay: Duration,
+    // how long full rows stay on the board before they're removed, on top of the entry delay
+    pub line_clear_delay: Duration,
+    // rotations pressed during the entry or line clear delay are applied as the piece spawns
+    pub initial_rotation: bool,
+    // a hold pressed during the entry or line clear delay swaps the piece as it spawns
+    pub initial_hold: bool,
+    // columns, from 4 up to board::MAX_WIDTH
+    pub width: usize,
+    pub visible_height: usize,
+    // rows above the visible ones that pieces spawn into, at least 2
+    pub buffer_height: usize,
+    // the level a game starts at, going up one every 10 lines from there
+    pub start_level: i32,
+    // lines to clear to finish the game, None to play until topping out
+    pub line_goal: Option<i32>,
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        return Rules {
+            rotation_system: RotationSystemKind::Srs,
+            randomizer: RandomizerKind::Bag7,
+            preview: 5,
+            partial_lock_out: false,
+            lock_delay: Duration::from_millis(500),
+            lock_reset: LockReset::ExtendedPlacement,
+            gravity: Gravity::Guideline,
+            entry_delay: Duration::from_secs(0),
+            line_clear_delay: Duration::from_secs(0),
+            initial_rotation: false,
+            initial_hold: false,
+            width: 10,
+            visible_height: 20,
+            buffer_height: 2,
+            start_level: 1,
+            line_goal: None,
+        };
+    }
 }
 
 pub struct PlaySpace {
-    space: [[SpaceState; 22]; 10],
+    rules: Rules,
+    state: GameState,
+    phase: Phase,
+    // time spent in the entry or line clear delay so far
+    phase_timer: Duration,
+    // the full rows shown during the line clear delay
+    clearing_rows: Vec<i32>,
+    board: Board,
     score: i32,
     lines_cleared: i32,
-    pub(crate) color: (f32, f32, f32),
+    level: i32,
+    rotation_system: Box<dyn RotationSystem>,
+    randomizer: Box<dyn Randomizer>,
+    // the pieces played with instead of the rules' rotation system, kept for restarts
+    piece_set: Option<PieceSet>,
+    // every random decision in a game comes from this, so a seed reproduces the whole game
+    seed: u64,
+    rng: Pcg64,
+    next_queue: Vec<PieceKind>,
     current_tetromino: Tetromino,
     current_tetromino_rotation: usize,
     // top left block
-    falling_position: (i32, usize),
-    time_since_movement: i32,
+    falling_position: (i32, i32),
+    // time not yet simulated, less than one frame
+    accumulator: Duration,
+    // fraction of a row gravity has moved the falling tetromino
+    gravity_progress: f64,
+    lock_timer: Duration,
+    lock_resets: u32,
+    // the lowest row the falling tetromino has reached, falling below it restarts the lock delay
+    lowest_row: i32,
+    events: Vec<GameEvent>,
+    held: Option<PieceKind>,
+    // only one hold is allowed per piece, until it locks
+    can_hold: bool,
+    // consecutive line clearing locks, -1 after a lock that clears nothing
+    combo: i32,
+    // consecutive tetrises and t-spin clears, -1 once a lesser clear breaks the chain
+    back_to_back: i32,
+    // the rotation turned from and the kick index used if the last successful action was a
+    // rotation, for t-spins
+    last_rotation: Option<(usize, usize)>,
+    // initial rotation and hold pressed during a delay, waiting for the next piece
+    buffered_rotation: Option<usize>,
+    buffered_hold: bool,
 }
 
 impl PlaySpace {
     pub fn initialize() -> PlaySpace {
-        let default = PlaySpace {
-            space: [[SpaceState::Empty; 22]; 10],
+        return PlaySpace::initialize_with_rules(Rules::default());
+    }
+
+    pub fn initialize_with_seed(seed: u64) -> PlaySpace {
+        return PlaySpace::initialize_with_rules_and_seed(Rules::default(), seed);
+    }
+
+    pub fn initialize_with_rules(rules: Rules) -> PlaySpace {
+        return PlaySpace::initialize_with_rules_and_seed(rules, rand::random());
+    }
+
+    pub fn initialize_with_rules_and_seed(rules: Rules, seed: u64) -> PlaySpace {
+        return PlaySpace::new(rules, seed, None);
+    }
+
+    // a game dealing the pieces of a set, which also decides their rotations and kicks
+    pub fn initialize_with_piece_set(rules: Rules, piece_set: PieceSet, seed: u64) -> PlaySpace {
+        return PlaySpace::new(rules, seed, Some(piece_set));
+    }
+
+    fn new(mut rules: Rules, seed: u64, piece_set: Option<PieceSet>) -> PlaySpace {
+        rules.preview = rules.preview.min(MAX_PREVIEW);
+        rules.width = rules.width.clamp(4, MAX_WIDTH);
+        rules.visible_height = rules.visible_height.max(4);
+        rules.buffer_height = rules.buffer_height.max(2);
+        rules.start_level = rules.start_level.max(1);
+        let (rotation_system, mut randomizer) = match &piece_set {
+            Some(set) => (
+                Box::new(set.clone()) as Box<dyn RotationSystem>,
+                rules.randomizer.build_for(&set.kinds()),
+            ),
+            None => (rules.rotation_system.build(), rules.randomizer.build()),
+        };
+        let mut rng = Pcg64::seed_from_u64(seed);
+        let first = randomizer.next(&mut rng);
+        let mut default = PlaySpace {
+            rules,
+            state: GameState::Playing,
+            phase: Phase::Falling,
+            phase_timer: Duration::from_secs(0),
+            clearing_rows: Vec::new(),
+            board: Board::new(rules.width, rules.visible_height + rules.buffer_height),
             score: 0,
             lines_cleared: 0,
-            color: (1.0, 1.0, 1.0),
-            current_tetromino: Tetromino::random(),
+            level: rules.start_level,
+            current_tetromino: Tetromino::new(first, &*rotation_system),
+            rotation_system,
+            randomizer,
+            piece_set,
+            seed,
+            rng,
+            next_queue: Vec::new(),
             current_tetromino_rotation: 0,
             falling_position: (4, 21),
-            time_since_movement: 0,
+            accumulator: Duration::from_secs(0),
+            gravity_progress: 0.0,
+            lock_timer: Duration::from_secs(0),
+            lock_resets: 0,
+            lowest_row: 0,
+            events: Vec::new(),
+            held: None,
+            can_hold: true,
+            combo: -1,
+            back_to_back: -1,
+            last_rotation: None,
+            buffered_rotation: None,
+            buffered_hold: false,
         };
+        default.next_queue = default.randomizer.peek(rules.preview, &mut default.rng);
+        default.spawn(first);
         return default;
     }
 
-    pub fn tick(&mut self, action: Action) {
+    pub fn seed(&self) -> u64 {
+        return self.seed;
+    }
+
+    pub fn state(&self) -> GameState {
+        return self.state;
+    }
+
+    pub fn phase(&self) -> Phase {
+        return self.phase;
+    }
+
+    // whether there's a piece in play, there isn't during the entry and line clear delays
+    pub fn piece_active(&self) -> bool {
+        return self.phase == Phase::Falling || self.phase == Phase::Locking;
+    }
+
+    // the rows counted from the bottom that are about to be removed, during the line clear delay
+    pub fn clearing_rows(&self) -> &[i32] {
+        return &self.clearing_rows;
+    }
+
+    pub fn rules(&self) -> Rules {
+        return self.rules;
+    }
+
+    pub fn score(&self) -> i32 {
+        return self.score;
+    }
+
+    pub fn lines_cleared(&self) -> i32 {
+        return self.lines_cleared;
+    }
+
+    pub fn level(&self) -> i32 {
+        return self.level;
+    }
+
+    // takes every event queued since the last call
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        return std::mem::take(&mut self.events);
+    }
+
+    pub fn current_tetromino(&self) -> &Tetromino {
+        return &self.current_tetromino;
+    }
+
+    pub fn current_tetromino_rotation(&self) -> usize {
+        return self.current_tetromino_rotation;
+    }
+
+    // top left of the current tetromino's box
+    pub fn falling_position(&self) -> (i32, i32) {
+        return self.falling_position;
+    }
+
+    // where the current tetromino would land if it were hard dropped now
+    pub fn ghost_position(&self) -> (i32, i32) {
+        let mut position = self.falling_position;
+        while self.can_fall_from(position) {
+            position.1 -= 1;
+        }
+        return position;
+    }
+
+    // starts a new game with the same rules and pieces and a fresh seed
+    pub fn restart(&mut self) {
+        *self = PlaySpace::new(self.rules, rand::random(), self.piece_set.take());
+    }
+
+    pub fn tick(&mut self, action: Action, elapsed: Duration) {
+        if let GameState::GameOver { .. } = self.state {
+            return;
+        }
+        self.act(action);
+        self.accumulator = (self.accumulator + elapsed).min(MAX_CATCH_UP);
+        while self.accumulator >= FRAME && self.state == GameState::Playing {
+            self.accumulator -= FRAME;
+            self.step();
+        }
+    }
+
+    fn act(&mut self, action: Action) {
+        if !self.piece_active() {
+            self.buffer(action);
+            return;
+        }
+        let grounded = !self.can_fall();
         let mut moved = false;
         match action {
             Action::MoveLeft => {
-                let mut can_move_left = true;
-                if self.falling_position.0 == -1 {
-                    can_move_left = false;
-                }
-                for i in 0..4 {
-                    for j in 0..4 {
-                        if let SpaceState::FallingTetromino =
-                            self.current_tetromino.map[self.current_tetromino_rotation][i][j]
-                        {
-                            if self.falling_position.0 as usize + j == 0 {
-                                can_move_left = false;
-                                break;
-                            }
-                            if let SpaceState::SettledTetromino(_) = self.space
-                                [self.falling_position.0 as usize + j - 1]
-                                [self.falling_position.1 - i]
-                            {
-                                can_move_left = false;
-                                break;
-                            }
-                        }
-                    }
-                    if !can_move_left {
-                        break;
-                    }
-                }
-                if can_move_left {
-                    self.falling_position.0 = self.falling_position.0 - 1;
+                let position = (self.falling_position.0 - 1, self.falling_position.1);
+                if self.fits(self.current_tetromino_rotation, position) {
+                    self.falling_position = position;
                     moved = true;
+                    self.last_rotation = None;
+                    self.events.push(GameEvent::Moved { position });
                 }
             }
             Action::MoveRight => {
-                let mut can_move_right = true;
-                for i in 0..4 {
-                    for j in 0..4 {
-                        if let SpaceState::FallingTetromino =
-                        self.current_tetromino.map[self.current_tetromino_rotation][i][j]
-                        {
-                            if (self.falling_position.0 as usize + j) as i32 == 9 {
-                                can_move_right = false;
-                                break;
-                            }
-                            if let SpaceState::SettledTetromino(_) = self.space
-                                [self.falling_position.0 as usize + j + 1]
-                                [self.falling_position.1 - i]
-                            {
-                                can_move_right = false;
-                                break;
-                            }
-                        }
-                    }
-                    if !can_move_right {
-                        break;
-                    }
-                }
-                if can_move_right {
-                    self.falling_position.0 = self.falling_position.0 + 1;
+                let position = (self.falling_position.0 + 1, self.falling_position.1);
+                if self.fits(self.current_tetromino_rotation, position) {
+                    self.falling_position = position;
                     moved = true;
+                    self.last_rotation = None;
+                    self.events.push(GameEvent::Moved { position });
                 }
             }
             Action::RotateClockwise => {
-                self.current_tetromino_rotation = (self.current_tetromino_rotation + 1) % 4;
-                for i in 0..4 {
-                    for j in 0..4 {
-                        if let SpaceState::FallingTetromino =
-                        self.current_tetromino.map[self.current_tetromino_rotation][i][j]
-                        {
-                            // TODO
-                        }
+                moved = self.rotate((self.current_tetromino_rotation + 1) % 4);
+            }
+            Action::RotateCounterClockwise => {
+                moved = self.rotate((self.current_tetromino_rotation + 3) % 4);
+            }
+            Action::Rotate180 => {
+                moved = self.rotate((self.current_tetromino_rotation + 2) % 4);
+            }
+            Action::SoftDrop if self.can_fall() => {
+                self.fall();
+                self.score += 1;
+                self.gravity_progress = 0.0;
+                self.events.push(GameEvent::Moved {
+                    position: self.falling_position,
+                });
+            }
+            Action::HardDrop => {
+                while self.can_fall() {
+                    self.fall();
+                    self.score += 2;
+                }
+                self.lock();
+                return;
+            }
+            Action::Hold if self.can_hold => {
+                let next = match self.held {
+                    Some(held) => held,
+                    None => self.next_piece(),
+                };
+                self.held = Some(self.current_tetromino.kind);
+                self.events.push(GameEvent::Hold {
+                    held: self.current_tetromino.kind,
+                });
+                self.spawn(next);
+                self.can_hold = false;
+                return;
+            }
+            _ => {}
+        }
+        if moved && (grounded || !self.can_fall()) {
+            match self.rules.lock_reset {
+                LockReset::ExtendedPlacement => {
+                    if self.lock_resets < MAX_LOCK_RESETS {
+                        self.lock_resets += 1;
+                        self.lock_timer = Duration::from_secs(0);
                     }
                 }
+                LockReset::Infinite => self.lock_timer = Duration::from_secs(0),
+                LockReset::StepReset => {}
             }
+        }
+        self.phase = self.piece_phase();
+    }
+
+    // remembers initial rotations and holds pressed while waiting for the next piece
+    fn buffer(&mut self, action: Action) {
+        match action {
+            Action::RotateClockwise if self.rules.initial_rotation => {
+                self.buffered_rotation = Some(1);
+            }
+            Action::RotateCounterClockwise if self.rules.initial_rotation => {
+                self.buffered_rotation = Some(3);
+            }
+            Action::Rotate180 if self.rules.initial_rotation => {
+                self.buffered_rotation = Some(2);
+            }
+            Action::Hold if self.rules.initial_hold => self.buffered_hold = true,
             _ => {}
         }
-        let can_fall = self.can_fall();
-        if !can_fall && moved {
-            self.time_since_movement = -1;
+    }
+
+    // advances the current phase by one frame
+    fn step(&mut self) {
+        match self.phase {
+            Phase::Spawning => {
+                self.phase_timer += FRAME;
+                if self.phase_timer >= self.rules.entry_delay {
+                    self.spawn_next();
+                }
+            }
+            Phase::LineClearing => {
+                self.phase_timer += FRAME;
+                if self.phase_timer >= self.rules.line_clear_delay {
+                    let rows = std::mem::take(&mut self.clearing_rows);
+                    self.board.remove_rows(&rows);
+                    self.wait_for_entry();
+                }
+            }
+            Phase::Falling | Phase::Locking => self.step_piece(),
         }
-        if self.time_since_movement > 10 {
-            if can_fall {
-                self.falling_position.1 -= 1;
-            } else {
-                self.space = self.space_with_falling_as_settled();
-                self.current_tetromino = Tetromino::random();
-                self.current_tetromino_rotation = 0;
-                self.falling_position = (4, 21);
+    }
+
+    // advances gravity and the lock delay by one frame
+    fn step_piece(&mut self) {
+        if self.can_fall() {
+            self.gravity_progress += self.gravity();
+            while self.gravity_progress >= 1.0 && self.can_fall() {
+                self.fall();
+                self.gravity_progress -= 1.0;
+            }
+            if !self.can_fall() {
+                self.gravity_progress = 0.0;
             }
-            self.time_since_movement = 0;
         } else {
-            self.time_since_movement += 1;
+            self.lock_timer += FRAME;
+            let out_of_resets = self.rules.lock_reset == LockReset::ExtendedPlacement
+                && self.lock_resets >= MAX_LOCK_RESETS;
+            if self.lock_timer >= self.rules.lock_delay || out_of_resets {
+                self.lock();
+                return;
+            }
         }
+        self.phase = self.piece_phase();
     }
 
-    pub fn space_with_falling_as_settled(&self) -> [[SpaceState; 22]; 10] {
-        let mut new_space = self.space.clone();
-        for i in 0..4 {
-            for j in 0..4 {
-                if let SpaceState::FallingTetromino =
-                self.current_tetromino.map[self.current_tetromino_rotation][i][j]
-                {
-                    new_space[(self.falling_position.0 + j as i32) as usize]
-                        [self.falling_position.1 - i] =
-                        SpaceState::SettledTetromino(self.current_tetromino.color);
+    fn piece_phase(&self) -> Phase {
+        if self.can_fall() {
+            return Phase::Falling;
+        }
+        return Phase::Locking;
+    }
+
+    pub fn held(&self) -> Option<PieceKind> {
+        return self.held;
+    }
+
+    pub fn next_queue(&self) -> &[PieceKind] {
+        return &self.next_queue;
+    }
+
+    // a piece's block map in the orientation it spawns in, for previews
+    pub fn piece_map(&self, kind: PieceKind) -> PieceMap {
+        return self
+            .rotation_system
+            .map(kind, self.rotation_system.spawn_rotation(kind));
+    }
+
+    // the settled blocks
+    pub fn board(&self) -> &Board {
+        return &self.board;
+    }
+
+    // the positions of the falling tetromino's blocks, none between pieces
+    pub fn falling_blocks(&self) -> Vec<(i32, i32)> {
+        let mut blocks = Vec::new();
+        if !self.piece_active() {
+            return blocks;
+        }
+        let map = self.current_tetromino.map[self.current_tetromino_rotation];
+        for (i, row) in map.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                if let SpaceState::FallingTetromino = cell {
+                    blocks.push((
+                        self.falling_position.0 + j as i32,
+                        self.falling_position.1 - i as i32,
+                    ));
                 }
             }
         }
-        return new_space;
+        return blocks;
     }
 
-    fn can_fall(&self) -> bool {
-        let mut lowest_in_col = [0; 4];
-        for i in 0..4 {
-            for j in 0..4 {
+    fn lock(&mut self) {
+        let mut blocks = 0;
+        let mut above_visible = 0;
+        for i in 0..BOX {
+            for j in 0..BOX {
                 if let SpaceState::FallingTetromino =
-                self.current_tetromino.map[self.current_tetromino_rotation][i][j]
+                    self.current_tetromino.map[self.current_tetromino_rotation][i][j]
                 {
-                    if i > lowest_in_col[j] {
-                        lowest_in_col[j] = i;
+                    blocks += 1;
+                    if self.falling_position.1 - i as i32 >= self.rules.visible_height as i32 {
+                        above_visible += 1;
                     }
                 }
             }
         }
-        for i in 0..4 {
-            let x_test = (self.falling_position.0 + i as i32) as usize;
-            if x_test > 9 {
-                continue;
+        let t_spin = self.t_spin();
+        self.board.place(
+            &self.current_tetromino.masks[self.current_tetromino_rotation],
+            self.falling_position,
+            self.current_tetromino.kind,
+        );
+        self.events.push(GameEvent::Locked {
+            kind: self.current_tetromino.kind,
+            rotation: self.current_tetromino_rotation,
+            position: self.falling_position,
+        });
+        if above_visible == blocks {
+            self.game_over(GameOverReason::LockOut);
+            return;
+        }
+        if above_visible > 0 && self.rules.partial_lock_out {
+            self.game_over(GameOverReason::PartialLockOut);
+            return;
+        }
+        self.can_hold = true;
+        let rows = self.board.full_rows();
+        self.score_clear(t_spin, &rows);
+        if !rows.is_empty() && self.rules.line_clear_delay > Duration::from_secs(0) {
+            self.phase = Phase::LineClearing;
+            self.phase_timer = Duration::from_secs(0);
+            self.clearing_rows = rows;
+        } else {
+            self.board.remove_rows(&rows);
+            self.wait_for_entry();
+        }
+    }
+
+    // starts the entry delay, spawning straight away when there is none, or ends the game once
+    // the cleared rows are gone if they reached the line goal
+    fn wait_for_entry(&mut self) {
+        self.phase = Phase::Spawning;
+        self.phase_timer = Duration::from_secs(0);
+        if let Some(goal) = self.rules.line_goal {
+            if self.lines_cleared >= goal {
+                self.game_over(GameOverReason::GoalReached);
+                return;
+            }
+        }
+        if self.rules.entry_delay == Duration::from_secs(0) {
+            self.spawn_next();
+        }
+    }
+
+    // spawns the next piece from the queue, applying any initial hold and rotation
+    fn spawn_next(&mut self) {
+        let mut next = self.next_piece();
+        if std::mem::take(&mut self.buffered_hold) {
+            let held = self.held.replace(next);
+            self.events.push(GameEvent::Hold { held: next });
+            next = match held {
+                Some(held) => held,
+                None => self.next_piece(),
+            };
+            self.can_hold = false;
+        }
+        self.spawn(next);
+        if let Some(turn) = self.buffered_rotation.take() {
+            if self.state == GameState::Playing {
+                self.rotate((self.current_tetromino_rotation + turn) % 4);
+            }
+        }
+    }
+
+    // rows fallen per frame
+    pub fn gravity(&self) -> f64 {
+        match self.rules.gravity {
+            Gravity::Guideline => {
+                // the curve is only defined up to level 20, where it's already past 20G
+                let level = self.level.min(20) as f64;
+                let seconds_per_row = (0.8 - (level - 1.0) * 0.007).powf(level - 1.0);
+                return FRAME.as_secs_f64() / seconds_per_row;
+            }
+            Gravity::Fixed(rows) => rows,
+        }
+    }
+
+    fn fall(&mut self) {
+        self.falling_position.1 -= 1;
+        self.last_rotation = None;
+        if self.falling_position.1 < self.lowest_row {
+            self.lowest_row = self.falling_position.1;
+            self.lock_timer = Duration::from_secs(0);
+            self.lock_resets = 0;
+        }
+    }
+
+    fn next_piece(&mut self) -> PieceKind {
+        let next = self.randomizer.next(&mut self.rng);
+        self.next_queue = self.randomizer.peek(self.rules.preview, &mut self.rng);
+        return next;
+    }
+
+    fn spawn(&mut self, kind: PieceKind) {
+        self.current_tetromino = Tetromino::new(kind, &*self.rotation_system);
+        self.current_tetromino_rotation = self.rotation_system.spawn_rotation(kind);
+        self.falling_position = self.rotation_system.spawn_position(
+            kind,
+            self.board.width(),
+            self.rules.visible_height,
+        );
+        self.lowest_row = self.falling_position.1;
+        self.gravity_progress = 0.0;
+        self.lock_timer = Duration::from_secs(0);
+        self.lock_resets = 0;
+        self.last_rotation = None;
+        if !self.fits(self.current_tetromino_rotation, self.falling_position) {
+            self.game_over(GameOverReason::BlockOut);
+            return;
+        }
+        self.phase = self.piece_phase();
+        self.events.push(GameEvent::PieceSpawned { kind });
+    }
+
+    fn game_over(&mut self, reason: GameOverReason) {
+        self.state = GameState::GameOver { reason };
+        self.events.push(GameEvent::GameOver { reason });
+    }
+
+    // scores the full rows along with any t-spin the locking piece made and the back-to-back,
+    // combo and perfect clear bonuses, before the rows are removed
+    fn score_clear(&mut self, t_spin: Option<TSpinKind>, rows: &[i32]) {
+        let cleared = rows.len() as i32;
+        let mut points = match t_spin {
+            Some(TSpinKind::Full) => [400, 800, 1200, 1600][cleared.min(3) as usize],
+            Some(TSpinKind::Mini) => [100, 200, 400, 400][cleared.min(3) as usize],
+            None => [0, 100, 300, 500, 800][cleared.min(4) as usize],
+        };
+        if let Some(kind) = t_spin {
+            self.events.push(GameEvent::TSpin {
+                kind,
+                lines: cleared,
+            });
+        }
+        if cleared == 0 {
+            self.score += points * self.level;
+            self.combo = -1;
+            return;
+        }
+        // tetrises, or bigger clears with pentominoes, and t-spins that clear lines keep a
+        // back-to-back chain going, any other clear breaks it while placing pieces without
+        // clearing leaves it alone
+        if cleared >= 4 || t_spin.is_some() {
+            self.back_to_back += 1;
+            if self.back_to_back > 0 {
+                points = points * 3 / 2;
+                self.events.push(GameEvent::BackToBack {
+                    chain: self.back_to_back,
+                });
             }
-            if self.falling_position.1 - lowest_in_col[i] == 0 {
-                return false;
+        } else {
+            self.back_to_back = -1;
+        }
+        self.combo += 1;
+        if self.combo > 0 {
+            points += 50 * self.combo;
+            self.events.push(GameEvent::Combo { combo: self.combo });
+        }
+        self.lines_cleared += cleared;
+        self.events.push(GameEvent::LinesCleared {
+            rows: rows.to_vec(),
+            kind: ClearKind::from_lines(cleared as usize),
+        });
+        if self.board.only_full_rows() {
+            points += match cleared {
+                1 => 800,
+                2 => 1200,
+                3 => 1800,
+                _ if self.back_to_back > 0 => 3200,
+                _ => 2000,
+            };
+            self.events.push(GameEvent::PerfectClear { lines: cleared });
+        }
+        self.score += points * self.level;
+        let level = self.rules.start_level + self.lines_cleared / 10;
+        if level > self.level {
+            self.level = level;
+            self.events.push(GameEvent::LevelUp { level });
+        }
+    }
+
+    // tries each kick offset in order, keeping the first one that fits
+    fn rotate(&mut self, rotation: usize) -> bool {
+        let kicks = self
+            .rotation_system
+            .kicks(
+                self.current_tetromino.kind,
+                self.current_tetromino_rotation,
+                rotation,
+            )
+            .to_vec();
+        for (kick_index, kick) in kicks.iter().enumerate() {
+            let position = (
+                self.falling_position.0 + kick.0,
+                self.falling_position.1 + kick.1,
+            );
+            if self.fits(rotation, position) {
+                self.last_rotation = Some((self.current_tetromino_rotation, kick_index));
+                self.current_tetromino_rotation = rotation;
+                self.falling_position = position;
+                self.events.push(GameEvent::Rotated {
+                    rotation,
+                    kick_index,
+                });
+                return true;
             }
-            let y_test = self.falling_position.1 - lowest_in_col[i] - 1;
-            match self.space[x_test][y_test] {
-                SpaceState::Empty => {}
-                _ => {
-                    return false;
+        }
+        return false;
+    }
+
+    // the 3-corner rule: a T rotated into place with at least three of the four diagonal corners
+    // around its center filled is a t-spin, a full one if both corners it points at are filled or
+    // the rotation system upgrades the kick it got there with
+    fn t_spin(&self) -> Option<TSpinKind> {
+        if self.current_tetromino.kind != PieceKind::T {
+            return None;
+        }
+        let (from, kick_index) = self.last_rotation?;
+        let upgraded = self.rotation_system.upgrades_t_spin(
+            PieceKind::T,
+            from,
+            self.current_tetromino_rotation,
+            kick_index,
+        );
+        let map = self.current_tetromino.map[self.current_tetromino_rotation];
+        let filled = |i: i32, j: i32| {
+            (0..BOX as i32).contains(&i)
+                && (0..BOX as i32).contains(&j)
+                && matches!(map[i as usize][j as usize], SpaceState::FallingTetromino)
+        };
+        // the center is the block with three neighbours, the front is the side it points to,
+        // opposite the one without a neighbour
+        let mut center = None;
+        for i in 0..BOX as i32 {
+            for j in 0..BOX as i32 {
+                let sides = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+                let open: Vec<_> = sides
+                    .iter()
+                    .filter(|(di, dj)| !filled(i + di, j + dj))
+                    .collect();
+                if filled(i, j) && open.len() == 1 {
+                    center = Some((i, j, (-open[0].0, -open[0].1)));
                 }
             }
         }
-        return true;
+        let (i, j, front) = center?;
+        let corners = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+        let mut occupied = 0;
+        let mut front_occupied = 0;
+        for (di, dj) in corners.iter() {
+            let x = self.falling_position.0 + j + dj;
+            let y = self.falling_position.1 - (i + di);
+            if self.board.blocked(x, y) {
+                occupied += 1;
+                if *di == front.0 || *dj == front.1 {
+                    front_occupied += 1;
+                }
+            }
+        }
+        if occupied < 3 {
+            return None;
+        }
+        if front_occupied == 2 || upgraded {
+            return Some(TSpinKind::Full);
+        }
+        return Some(TSpinKind::Mini);
+    }
+
+    fn can_fall(&self) -> bool {
+        return self.can_fall_from(self.falling_position);
+    }
+
+    fn can_fall_from(&self, position: (i32, i32)) -> bool {
+        let below = (position.0, position.1 - 1);
+        return self.fits(self.current_tetromino_rotation, below);
+    }
+
+    // whether the current tetromino in the given rotation and position stays inside the walls
+    // and floor without overlapping any settled blocks
+    fn fits(&self, rotation: usize, position: (i32, i32)) -> bool {
+        return self
+            .board
+            .fits(&self.current_tetromino.masks[rotation], position);
     }
 }
 
-struct Tetromino {
-    color: (f32, f32, f32),
-    map: [[[SpaceState; 4]; 4]; 4], // [rotation][x][y]
+#[derive(Copy, Clone)]
+pub struct Tetromino {
+    kind: PieceKind,
+    map: [PieceMap; 4],    // [rotation][row][column]
+    masks: [PieceMask; 4], // [rotation][row]
 }
 
 impl Tetromino {
-    pub fn random() -> Tetromino {
-        let mut r: f64 = random();
-        r = r * 7.0;
-        if r <= 1.0 {
-            return Tetromino::i();
-        } else if r <= 2.0 {
-            return Tetromino::o();
-        } else if r <= 3.0 {
-            return Tetromino::t();
-        } else if r <= 4.0 {
-            return Tetromino::s();
-        } else if r <= 5.0 {
-            return Tetromino::z();
-        } else if r <= 6.0 {
-            return Tetromino::j();
-        } else {
-            return Tetromino::l();
-        }
-    }
-
-    fn i() -> Tetromino {
-        let i = Tetromino {
-            color: (0.0, 1.0, 1.0),
-            map: [
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-            ],
-        };
-        return i;
-    }
-    fn o() -> Tetromino {
-        let o = Tetromino {
-            color: (1.0, 1.0, 0.0),
-            map: [
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-            ],
-        };
-        return o;
-    }
-    fn t() -> Tetromino {
-        let t = Tetromino {
-            color: (0.5, 0.0, 0.5),
-            map: [
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-            ],
-        };
-        return t;
-    }
-    fn s() -> Tetromino {
-        let s = Tetromino {
-            color: (0.0, 1.0, 0.0),
-            map: [
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-            ],
-        };
-        return s;
-    }
-    fn z() -> Tetromino {
-        let z = Tetromino {
-            color: (1.0, 0.0, 0.0),
-            map: [
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-            ],
-        };
-        return z;
-    }
-    fn j() -> Tetromino {
-        let j = Tetromino {
-            color: (0.0, 0.0, 1.0),
-            map: [
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-            ],
+    pub fn new(kind: PieceKind, rotation_system: &dyn RotationSystem) -> Tetromino {
+        let mut map = [[[SpaceState::Empty; BOX]; BOX]; 4];
+        let mut masks = [[0; BOX]; 4];
+        for rotation in 0..4 {
+            map[rotation] = rotation_system.map(kind, rotation);
+            masks[rotation] = board::mask_from_map(&map[rotation]);
+        }
+        return Tetromino { kind, map, masks };
+    }
+
+    pub fn kind(&self) -> PieceKind {
+        return self.kind;
+    }
+
+    pub fn map(&self, rotation: usize) -> PieceMap {
+        return self.map[rotation];
+    }
+
+    pub fn mask(&self, rotation: usize) -> PieceMask {
+        return self.masks[rotation];
+    }
+}
+
+#[cfg(test)]
+impl PlaySpace {
+    // a game on a board built from rows given top first, '#' marking a settled block, with a
+    // piece of the given kind in play at rotation and position
+    pub(crate) fn with_stack(
+        rows: &[&str],
+        kind: PieceKind,
+        rotation: usize,
+        position: (i32, i32),
+    ) -> PlaySpace {
+        let mut play_space = PlaySpace::initialize_with_seed(0);
+        play_space.set_stack(rows, kind, rotation, position);
+        return play_space;
+    }
+
+    // the same for a game that's already set up, e.g. with a PieceSet
+    pub(crate) fn set_stack(
+        &mut self,
+        rows: &[&str],
+        kind: PieceKind,
+        rotation: usize,
+        position: (i32, i32),
+    ) {
+        for (i, row) in rows.iter().enumerate() {
+            let mut mask = [0; BOX];
+            for (x, block) in row.chars().enumerate() {
+                if block == '#' {
+                    mask[0] |= 1 << x;
+                }
+            }
+            let y = (rows.len() - 1 - i) as i32;
+            self.board.place(&mask, (0, y), PieceKind::Garbage);
+        }
+        self.current_tetromino = Tetromino::new(kind, &*self.rotation_system);
+        self.current_tetromino_rotation = rotation;
+        self.falling_position = position;
+        self.lowest_row = position.1;
+        self.phase = self.piece_phase();
+        self.events.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_TIME: Duration = Duration::from_secs(0);
+
+    // spreads pieces over the board so the game lasts
+    fn drop_at(play_space: &mut PlaySpace, n: usize) {
+        let shift = n % 9;
+        for _ in 0..4 {
+            play_space.tick(Action::MoveLeft, NO_TIME);
+        }
+        for _ in 0..shift {
+            play_space.tick(Action::MoveRight, NO_TIME);
+        }
+        play_space.tick(Action::HardDrop, NO_TIME);
+    }
+
+    #[test]
+    fn same_seed_deals_the_same_pieces() {
+        for seed in 0..10 {
+            let mut first = PlaySpace::initialize_with_seed(seed);
+            let mut second = PlaySpace::initialize_with_seed(seed);
+            let mut drops = 0;
+            while first.state() == GameState::Playing {
+                assert_eq!(first.next_queue(), second.next_queue());
+                assert_eq!(
+                    first.current_tetromino().kind(),
+                    second.current_tetromino().kind()
+                );
+                drop_at(&mut first, drops);
+                drop_at(&mut second, drops);
+                drops += 1;
+            }
+            assert_eq!(first.state(), second.state());
+            assert_eq!(first.score(), second.score());
+            assert!(drops >= 10, "only {} drops", drops);
+        }
+    }
+
+    #[test]
+    fn different_seeds_deal_different_pieces() {
+        let first = PlaySpace::initialize_with_seed(1);
+        let second = PlaySpace::initialize_with_seed(2);
+        assert_ne!(first.next_queue(), second.next_queue());
+    }
+
+    // turns the piece, hard drops it and returns the t-spin it scored, if any
+    fn spin(play_space: &mut PlaySpace, action: Action) -> Option<(TSpinKind, i32)> {
+        play_space.tick(action, NO_TIME);
+        play_space.tick(Action::HardDrop, NO_TIME);
+        return play_space
+            .drain_events()
+            .iter()
+            .find_map(|event| match event {
+                GameEvent::TSpin { kind, lines } => Some((*kind, *lines)),
+                _ => None,
+            });
+    }
+
+    #[test]
+    fn t_spin_double() {
+        let rows = ["###..#....", "###...####", "####.#####"];
+        let mut play_space = PlaySpace::with_stack(&rows, PieceKind::T, 1, (2, 3));
+        assert_eq!(
+            spin(&mut play_space, Action::RotateClockwise),
+            Some((TSpinKind::Full, 2))
+        );
+        assert_eq!(play_space.score(), 1200);
+    }
+
+    #[test]
+    fn t_spin_mini_with_one_front_corner() {
+        // kicked against the left wall with the floor behind it and one block in front
+        let rows = [".#########"];
+        let mut play_space = PlaySpace::with_stack(&rows, PieceKind::T, 0, (0, 2));
+        assert_eq!(
+            spin(&mut play_space, Action::RotateClockwise),
+            Some((TSpinKind::Mini, 1))
+        );
+    }
+
+    #[test]
+    fn t_spin_triple_kick_is_full() {
+        // the fifth kick drops the T two rows into the slot with only one front corner filled
+        let rows = [
+            "....#.....",
+            "..........",
+            "####.#####",
+            "###..#####",
+            "###..#####",
+        ];
+        let mut play_space = PlaySpace::with_stack(&rows, PieceKind::T, 0, (2, 4));
+        assert_eq!(
+            spin(&mut play_space, Action::RotateCounterClockwise),
+            Some((TSpinKind::Full, 2))
+        );
+    }
+
+    #[test]
+    fn t_spin_180_kick_is_not_upgraded() {
+        // the SRS+ 180 kick from R to L also moves one column and two rows, the T is left in the
+        // air so its corners are checked as they would be on locking there
+        let rows = [
+            "...#.#....",
+            "..........",
+            ".....#....",
+            "..#.......",
+            "....#.....",
+        ];
+        let mut play_space = PlaySpace::with_stack(&rows, PieceKind::T, 1, (2, 2));
+        play_space.tick(Action::Rotate180, NO_TIME);
+        assert_eq!(play_space.current_tetromino_rotation(), 3);
+        assert_eq!(play_space.falling_position(), (3, 4));
+        assert_eq!(play_space.t_spin(), Some(TSpinKind::Mini));
+    }
+
+    #[test]
+    fn piece_set_kicks_never_upgrade_t_spins() {
+        // the standard set's fifth kick is a plain step down and to the left, here it takes the T
+        // against the wall with only one front corner filled
+        let kicks = vec![(0, 0), (-1, 0), (1, 0), (0, -1), (-1, -1), (1, -1)];
+        let mut set = PieceSet::new(kicks);
+        let cells: Vec<String> = [".#.", "###", "..."]
+            .iter()
+            .map(|row| row.to_string())
+            .collect();
+        set.add("T", &cells, None, (0, 0)).unwrap();
+        let mut play_space = PlaySpace::initialize_with_piece_set(Rules::default(), set, 0);
+        let rows = ["##........", "..........", "..#.......", ".#........"];
+        play_space.set_stack(&rows, PieceKind::T, 2, (0, 3));
+        play_space.tick(Action::RotateCounterClockwise, NO_TIME);
+        assert_eq!(play_space.falling_position(), (-1, 2));
+        assert_eq!(
+            spin(&mut play_space, Action::None),
+            Some((TSpinKind::Mini, 0))
+        );
+    }
+
+    // puts a piece right where it lands and hard drops it, so only its clear scores, returning
+    // what happened
+    fn place(
+        play_space: &mut PlaySpace,
+        kind: PieceKind,
+        rotation: usize,
+        position: (i32, i32),
+    ) -> Vec<GameEvent> {
+        play_space.set_stack(&[], kind, rotation, position);
+        play_space.tick(Action::HardDrop, NO_TIME);
+        return play_space.drain_events();
+    }
+
+    #[test]
+    fn combos_add_50_per_combo_per_level() {
+        let rows = ["######....", "######....", "######....", "......####"];
+        let mut play_space = PlaySpace::with_stack(&rows, PieceKind::I, 0, (6, 21));
+        // as if the game had started at level 2
+        play_space.level = 2;
+        let mut combos = Vec::new();
+        for score in [200, 500, 900].iter() {
+            let events = place(&mut play_space, PieceKind::I, 0, (6, 2));
+            combos.extend(events.iter().filter_map(|event| match event {
+                GameEvent::Combo { combo } => Some(*combo),
+                _ => None,
+            }));
+            assert_eq!(play_space.score(), *score);
+        }
+        assert_eq!(combos, vec![1, 2]);
+    }
+
+    #[test]
+    fn back_to_back_tetris_is_worth_half_again() {
+        let rows = ["#########."; 8];
+        let mut play_space = PlaySpace::with_stack(&rows, PieceKind::I, 0, (6, 21));
+        place(&mut play_space, PieceKind::I, 1, (7, 3));
+        assert_eq!(play_space.score(), 800);
+        // locking without a clear leaves the chain alone
+        place(&mut play_space, PieceKind::O, 0, (-1, 5));
+        assert_eq!(play_space.score(), 800);
+        let events = place(&mut play_space, PieceKind::I, 1, (7, 3));
+        assert_eq!(play_space.score(), 2000);
+        assert!(events.contains(&GameEvent::BackToBack { chain: 1 }));
+        assert!(events.contains(&GameEvent::LinesCleared {
+            rows: vec![0, 1, 2, 3],
+            kind: ClearKind::Tetris,
+        }));
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, GameEvent::Combo { .. })));
+    }
+
+    #[test]
+    fn lesser_clears_break_back_to_back() {
+        let mut rows = vec!["#########."; 9];
+        rows[4] = "#####....#";
+        let mut play_space = PlaySpace::with_stack(&rows, PieceKind::I, 0, (6, 21));
+        let mut events = place(&mut play_space, PieceKind::I, 1, (7, 8));
+        events.extend(place(&mut play_space, PieceKind::O, 0, (-1, 6)));
+        events.extend(place(&mut play_space, PieceKind::I, 0, (5, 5)));
+        assert_eq!(play_space.score(), 900);
+        events.extend(place(&mut play_space, PieceKind::O, 0, (1, 5)));
+        events.extend(place(&mut play_space, PieceKind::I, 1, (7, 3)));
+        assert_eq!(play_space.score(), 1700);
+        assert_eq!(play_space.lines_cleared(), 9);
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, GameEvent::BackToBack { .. })));
+    }
+
+    // the score of hard dropping the piece on the stack, which must clear everything
+    fn perfect_clear(rows: &[&str], kind: PieceKind, rotation: usize, position: (i32, i32)) -> i32 {
+        let mut play_space = PlaySpace::with_stack(rows, kind, rotation, position);
+        play_space.tick(Action::HardDrop, NO_TIME);
+        let lines = rows.len() as i32;
+        assert!(play_space
+            .drain_events()
+            .contains(&GameEvent::PerfectClear { lines }));
+        return play_space.score();
+    }
+
+    #[test]
+    fn perfect_clears_score_by_lines() {
+        let single = ["######...."];
+        assert_eq!(perfect_clear(&single, PieceKind::I, 0, (6, 1)), 100 + 800);
+        let double = ["########..", "########.."];
+        assert_eq!(perfect_clear(&double, PieceKind::O, 0, (7, 1)), 300 + 1200);
+        let triple = ["########.#", "########.#", "########.."];
+        assert_eq!(perfect_clear(&triple, PieceKind::L, 1, (7, 2)), 500 + 1800);
+        let tetris = ["#########."; 4];
+        assert_eq!(perfect_clear(&tetris, PieceKind::I, 1, (7, 3)), 800 + 2000);
+    }
+
+    #[test]
+    fn back_to_back_tetris_perfect_clear() {
+        let rows = ["#########."; 4];
+        let mut play_space = PlaySpace::with_stack(&rows, PieceKind::I, 1, (7, 3));
+        // a chain already going
+        play_space.back_to_back = 0;
+        play_space.tick(Action::HardDrop, NO_TIME);
+        assert_eq!(play_space.score(), 800 * 3 / 2 + 3200);
+    }
+
+    #[test]
+    fn five_line_clears_keep_back_to_back() {
+        let mut set = PieceSet::new(vec![]);
+        let rows: Vec<String> = (0..5).map(|_| "#".to_string()).collect();
+        set.add("I5", &rows, None, (0, 0)).unwrap();
+        let mut play_space = PlaySpace::initialize_with_piece_set(Rules::default(), set, 0);
+        // ten rows with a well in the right column, filled by two upright I pentominoes
+        for y in 0..10 {
+            let row = [0b1_1111_1111, 0, 0, 0, 0];
+            play_space.board.place(&row, (0, y), PieceKind::Garbage);
+        }
+        for _ in 0..2 {
+            for _ in 0..10 {
+                play_space.tick(Action::MoveRight, NO_TIME);
+            }
+            play_space.tick(Action::HardDrop, NO_TIME);
+        }
+        let chains: Vec<i32> = play_space
+            .drain_events()
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::BackToBack { chain } => Some(*chain),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(play_space.lines_cleared(), 10);
+        assert_eq!(chains, vec![1]);
+    }
+
+    // a T in play on the given stack under the given lock reset, with 20G so it never hangs in
+    // the air
+    fn grounded(lock_reset: LockReset, rows: &[&str], position: (i32, i32)) -> PlaySpace {
+        let rules = Rules {
+            lock_reset,
+            gravity: Gravity::Fixed(20.0),
+            ..Default::default()
         };
-        return j;
-    }
-    fn l() -> Tetromino {
-        let l = Tetromino {
-            color: (1.0, 0.5, 0.0),
-            map: [
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-                [
-                    [
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::FallingTetromino,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                    [
-                        SpaceState::Empty,
-                        SpaceState::FallingTetromino,
-                        SpaceState::Empty,
-                        SpaceState::Empty,
-                    ],
-                ],
-            ],
+        let mut play_space = PlaySpace::initialize_with_rules_and_seed(rules, 0);
+        play_space.set_stack(rows, PieceKind::T, 0, position);
+        return play_space;
+    }
+
+    fn locked(play_space: &mut PlaySpace) -> bool {
+        return play_space
+            .drain_events()
+            .iter()
+            .any(|event| matches!(event, GameEvent::Locked { .. }));
+    }
+
+    // shifts the piece back and forth, left first
+    fn shuffle(n: usize) -> Action {
+        return [Action::MoveLeft, Action::MoveRight][n % 2];
+    }
+
+    #[test]
+    fn extended_placement_locks_after_the_last_reset() {
+        let mut play_space = grounded(LockReset::ExtendedPlacement, &[], (4, 1));
+        for n in 0..MAX_LOCK_RESETS as usize - 1 {
+            play_space.tick(shuffle(n), FRAME * 12);
+            assert!(!locked(&mut play_space), "locked after {} moves", n + 1);
+        }
+        // the last reset still counts, the next frame on the ground locks
+        play_space.tick(Action::MoveLeft, NO_TIME);
+        assert!(!locked(&mut play_space));
+        play_space.tick(Action::None, FRAME);
+        assert!(locked(&mut play_space));
+    }
+
+    #[test]
+    fn infinite_lock_reset_never_runs_out() {
+        let mut play_space = grounded(LockReset::Infinite, &[], (4, 1));
+        for n in 0..40 {
+            play_space.tick(shuffle(n), FRAME * 12);
+            assert!(!locked(&mut play_space), "locked after {} moves", n + 1);
+        }
+        play_space.tick(Action::None, FRAME * 12);
+        assert!(!locked(&mut play_space));
+        play_space.tick(Action::None, FRAME * 12);
+        assert!(locked(&mut play_space));
+    }
+
+    #[test]
+    fn step_reset_ignores_moves() {
+        let mut play_space = grounded(LockReset::StepReset, &[], (4, 1));
+        play_space.tick(Action::MoveLeft, FRAME * 12);
+        play_space.tick(Action::MoveRight, FRAME * 12);
+        assert!(!locked(&mut play_space));
+        play_space.tick(Action::MoveLeft, FRAME * 12);
+        assert!(locked(&mut play_space));
+    }
+
+    #[test]
+    fn step_reset_restarts_on_a_new_lowest_row() {
+        // resting on a one block ledge, one step right drops it to the floor
+        let rows = ["#........."];
+        let mut play_space = grounded(LockReset::StepReset, &rows, (0, 2));
+        play_space.tick(Action::None, FRAME * 12);
+        play_space.tick(Action::None, FRAME * 12);
+        play_space.tick(Action::MoveRight, NO_TIME);
+        play_space.tick(Action::None, FRAME * 12);
+        play_space.tick(Action::None, FRAME * 12);
+        assert_eq!(play_space.falling_position(), (1, 1));
+        assert!(!locked(&mut play_space));
+        play_space.tick(Action::None, FRAME * 8);
+        assert!(locked(&mut play_space));
+    }
+
+    // a game with a 100ms entry delay and 200ms line clear delay
+    fn delayed(initial_rotation: bool, initial_hold: bool) -> PlaySpace {
+        let rules = Rules {
+            entry_delay: Duration::from_millis(100),
+            line_clear_delay: Duration::from_millis(200),
+            initial_rotation,
+            initial_hold,
+            ..Default::default()
         };
-        return l;
+        return PlaySpace::initialize_with_rules_and_seed(rules, 0);
+    }
+
+    #[test]
+    fn phases_run_through_the_delays() {
+        let mut play_space = delayed(false, false);
+        play_space.set_stack(&["######...."], PieceKind::I, 0, (6, 3));
+        assert_eq!(play_space.phase(), Phase::Falling);
+        play_space.tick(Action::SoftDrop, NO_TIME);
+        play_space.tick(Action::SoftDrop, NO_TIME);
+        assert_eq!(play_space.phase(), Phase::Locking);
+        for _ in 0..3 {
+            play_space.tick(Action::None, FRAME * 10);
+        }
+        assert_eq!(play_space.phase(), Phase::LineClearing);
+        assert!(!play_space.piece_active());
+        // the full row stays on the board until the delay is over
+        play_space.tick(Action::None, FRAME * 11);
+        assert_eq!(play_space.phase(), Phase::LineClearing);
+        assert_eq!(play_space.clearing_rows(), &[0]);
+        assert_eq!(play_space.board().full_rows(), vec![0]);
+        play_space.tick(Action::None, FRAME);
+        assert_eq!(play_space.phase(), Phase::Spawning);
+        assert!(play_space.clearing_rows().is_empty());
+        assert!(play_space.board().full_rows().is_empty());
+        play_space.drain_events();
+        play_space.tick(Action::None, FRAME * 5);
+        assert_eq!(play_space.phase(), Phase::Spawning);
+        play_space.tick(Action::None, FRAME);
+        assert_eq!(play_space.phase(), Phase::Falling);
+        assert!(play_space
+            .drain_events()
+            .iter()
+            .any(|event| matches!(event, GameEvent::PieceSpawned { .. })));
+    }
+
+    #[test]
+    fn initial_rotation_needs_its_rule() {
+        for &initial_rotation in [false, true].iter() {
+            let mut play_space = delayed(initial_rotation, false);
+            play_space.tick(Action::HardDrop, NO_TIME);
+            assert_eq!(play_space.phase(), Phase::Spawning);
+            play_space.tick(Action::RotateClockwise, NO_TIME);
+            play_space.tick(Action::None, FRAME * 6);
+            assert_eq!(play_space.phase(), Phase::Falling);
+            let rotation = if initial_rotation { 1 } else { 0 };
+            assert_eq!(play_space.current_tetromino_rotation(), rotation);
+        }
+    }
+
+    #[test]
+    fn initial_hold_needs_its_rule() {
+        for &initial_hold in [false, true].iter() {
+            let mut play_space = delayed(false, initial_hold);
+            play_space.tick(Action::HardDrop, NO_TIME);
+            let next = play_space.next_queue()[..2].to_vec();
+            play_space.tick(Action::Hold, NO_TIME);
+            play_space.tick(Action::None, FRAME * 6);
+            let current = play_space.current_tetromino().kind();
+            if initial_hold {
+                assert_eq!(play_space.held(), Some(next[0]));
+                assert_eq!(current, next[1]);
+            } else {
+                assert_eq!(play_space.held(), None);
+                assert_eq!(current, next[0]);
+            }
+        }
+    }
+
+    #[test]
+    fn no_t_spin_without_a_rotation() {
+        let rows = ["###..#....", "###...####", "####.#####"];
+        let mut play_space = PlaySpace::with_stack(&rows, PieceKind::T, 2, (3, 2));
+        assert_eq!(spin(&mut play_space, Action::None), None);
     }
 }
@@ -0,0 +1,197 @@
+use crate::tetris::{PieceKind, PieceMap, SpaceState, BOX};
+
+// rows are u32 bitmasks, so no wider than this
+pub const MAX_WIDTH: usize = 32;
+
+// a piece in one rotation as one bitmask per row of its box, top row first, bit j set for a
+// block in column j
+pub type PieceMask = [u16; BOX];
+
+// the settled blocks, one bitmask per row counted from the bottom with bit x set for a block in
+// column x, and the kind of each block kept apart so collision checks only touch the masks
+#[derive(Clone, PartialEq, Debug)]
+pub struct Board {
+    width: usize,
+    // the visible rows plus the buffer above them that pieces spawn into
+    height: usize,
+    // a row with every column filled
+    full: u32,
+    rows: Vec<u32>,
+    // row by row from the bottom, None wherever the row's bit is clear so equal boards compare
+    // equal
+    kinds: Vec<Option<PieceKind>>,
+}
+
+impl Board {
+    // an empty board, panicking unless width is from 1 up to MAX_WIDTH
+    pub fn new(width: usize, height: usize) -> Board {
+        assert!(
+            (1..=MAX_WIDTH).contains(&width),
+            "board width {} is outside 1 to {}",
+            width,
+            MAX_WIDTH
+        );
+        return Board {
+            width,
+            height,
+            full: u32::MAX >> (MAX_WIDTH - width),
+            rows: vec![0; height],
+            kinds: vec![None; width * height],
+        };
+    }
+
+    pub fn width(&self) -> usize {
+        return self.width;
+    }
+
+    pub fn height(&self) -> usize {
+        return self.height;
+    }
+
+    pub fn row(&self, y: usize) -> u32 {
+        return self.rows[y];
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> SpaceState {
+        match self.kinds[y * self.width + x] {
+            Some(kind) => SpaceState::SettledTetromino(kind),
+            None => SpaceState::Empty,
+        }
+    }
+
+    // whether a block at x, y would hit a wall, the floor or a settled block, the space above the
+    // top row is open
+    pub fn blocked(&self, x: i32, y: i32) -> bool {
+        if x < 0 || x >= self.width as i32 || y < 0 {
+            return true;
+        }
+        return (y as usize) < self.height && self.rows[y as usize] & 1 << x != 0;
+    }
+
+    // whether a piece with its box's top left corner at position stays inside the walls, floor
+    // and top row without overlapping any settled blocks
+    pub fn fits(&self, mask: &PieceMask, position: (i32, i32)) -> bool {
+        for (i, &row) in mask.iter().enumerate() {
+            if row == 0 {
+                continue;
+            }
+            let y = position.1 - i as i32;
+            if y < 0 || y >= self.height as i32 {
+                return false;
+            }
+            match self.shift(row, position.0) {
+                Some(shifted) => {
+                    if self.rows[y as usize] & shifted != 0 {
+                        return false;
+                    }
+                }
+                None => return false,
+            }
+        }
+        return true;
+    }
+
+    // settles a piece that fits at position
+    pub fn place(&mut self, mask: &PieceMask, position: (i32, i32), kind: PieceKind) {
+        for (i, &row) in mask.iter().enumerate() {
+            if row == 0 {
+                continue;
+            }
+            let y = (position.1 - i as i32) as usize;
+            if let Some(shifted) = self.shift(row, position.0) {
+                self.rows[y] |= shifted;
+                for x in 0..self.width {
+                    if shifted & 1 << x != 0 {
+                        self.kinds[y * self.width + x] = Some(kind);
+                    }
+                }
+            }
+        }
+    }
+
+    // the rows counted from the bottom with no empty space left in them
+    pub fn full_rows(&self) -> Vec<i32> {
+        return (0..self.height)
+            .filter(|&y| self.rows[y] == self.full)
+            .map(|y| y as i32)
+            .collect();
+    }
+
+    // removes the given rows, shifting everything above them down
+    pub fn remove_rows(&mut self, rows: &[i32]) {
+        // from the top down so the rows still to be removed don't move
+        for &y in rows.iter().rev() {
+            let y = y as usize;
+            self.rows.remove(y);
+            self.rows.push(0);
+            self.kinds.drain(y * self.width..(y + 1) * self.width);
+            self.kinds.resize(self.width * self.height, None);
+        }
+    }
+
+    // whether nothing would be left once the full rows are removed
+    pub fn only_full_rows(&self) -> bool {
+        return self.rows.iter().all(|&row| row == 0 || row == self.full);
+    }
+
+    // a row of a piece's mask moved so its box starts at column x, or None if that pushes a
+    // block through a wall
+    fn shift(&self, row: u16, x: i32) -> Option<u32> {
+        if row == 0 {
+            return Some(0);
+        }
+        if x <= -(BOX as i32) || x >= self.width as i32 {
+            return None;
+        }
+        let shifted = if x >= 0 {
+            (row as u64) << x
+        } else {
+            if row & ((1 << -x) - 1) != 0 {
+                return None;
+            }
+            (row >> -x) as u64
+        };
+        if shifted & !(self.full as u64) != 0 {
+            return None;
+        }
+        return Some(shifted as u32);
+    }
+}
+
+// the row masks of a block map
+pub fn mask_from_map(map: &PieceMap) -> PieceMask {
+    let mut mask = [0; BOX];
+    for (i, row) in map.iter().enumerate() {
+        for (j, cell) in row.iter().enumerate() {
+            if let SpaceState::FallingTetromino = cell {
+                mask[i] |= 1 << j;
+            }
+        }
+    }
+    return mask;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_width_rows_fill_every_bit() {
+        let mut board = Board::new(MAX_WIDTH, 4);
+        board.place(&[u16::MAX, 0, 0, 0, 0], (0, 0), PieceKind::Garbage);
+        board.place(&[u16::MAX, 0, 0, 0, 0], (16, 0), PieceKind::Garbage);
+        assert_eq!(board.full_rows(), vec![0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "board width 0")]
+    fn zero_width_is_refused() {
+        Board::new(0, 20);
+    }
+
+    #[test]
+    #[should_panic(expected = "board width 33")]
+    fn too_wide_is_refused() {
+        Board::new(MAX_WIDTH + 1, 20);
+    }
+}
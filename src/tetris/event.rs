@@ -0,0 +1,80 @@
+use crate::tetris::{GameOverReason, PieceKind};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ClearKind {
+    Single,
+    Double,
+    Triple,
+    Tetris,
+}
+
+impl ClearKind {
+    pub fn from_lines(lines: usize) -> ClearKind {
+        match lines {
+            1 => ClearKind::Single,
+            2 => ClearKind::Double,
+            3 => ClearKind::Triple,
+            _ => ClearKind::Tetris,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TSpinKind {
+    Full,
+    Mini,
+}
+
+// things that happened during a tick, queued on the PlaySpace until drained
+#[derive(Clone, PartialEq, Debug)]
+pub enum GameEvent {
+    PieceSpawned {
+        kind: PieceKind,
+    },
+    // the falling tetromino was shifted or soft dropped by the player
+    Moved {
+        position: (i32, i32),
+    },
+    Rotated {
+        rotation: usize,
+        // which of the rotation system's kick offsets was used, 0 being no kick
+        kick_index: usize,
+    },
+    Locked {
+        kind: PieceKind,
+        rotation: usize,
+        position: (i32, i32),
+    },
+    LinesCleared {
+        // the cleared rows counted from the bottom, as they were before clearing
+        rows: Vec<i32>,
+        kind: ClearKind,
+    },
+    // a T locked in by a rotation, pushed before the LinesCleared it scored along with
+    TSpin {
+        kind: TSpinKind,
+        lines: i32,
+    },
+    // the clear continued a chain of tetrises and t-spin clears, worth half as much again
+    BackToBack {
+        // 1 for the second difficult clear in a row
+        chain: i32,
+    },
+    Combo {
+        // 1 for the second line clearing lock in a row
+        combo: i32,
+    },
+    // the clear left the play space completely empty
+    PerfectClear {
+        lines: i32,
+    },
+    LevelUp {
+        level: i32,
+    },
+    Hold {
+        held: PieceKind,
+    },
+    GameOver {
+        reason: GameOverReason,
+    },
+}
@@ -0,0 +1,279 @@
+use crate::tetris::input::InputHandler;
+use crate::tetris::Action;
+use std::collections::VecDeque;
+
+// the buttons of a standard layout controller, named after their position rather than their
+// label since those differ between brands
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Button {
+    South,
+    East,
+    North,
+    West,
+    LeftTrigger,
+    LeftTrigger2,
+    RightTrigger,
+    RightTrigger2,
+    Select,
+    Start,
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl Button {
+    pub fn from_name(name: &str) -> Option<Button> {
+        match name.to_lowercase().as_str() {
+            "south" => Some(Button::South),
+            "east" => Some(Button::East),
+            "north" => Some(Button::North),
+            "west" => Some(Button::West),
+            "lefttrigger" => Some(Button::LeftTrigger),
+            "lefttrigger2" => Some(Button::LeftTrigger2),
+            "righttrigger" => Some(Button::RightTrigger),
+            "righttrigger2" => Some(Button::RightTrigger2),
+            "select" => Some(Button::Select),
+            "start" => Some(Button::Start),
+            "leftthumb" => Some(Button::LeftThumb),
+            "rightthumb" => Some(Button::RightThumb),
+            "dpadup" => Some(Button::DPadUp),
+            "dpaddown" => Some(Button::DPadDown),
+            "dpadleft" => Some(Button::DPadLeft),
+            "dpadright" => Some(Button::DPadRight),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Axis {
+    LeftStickX,
+    // positive is up
+    LeftStickY,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GamepadEvent {
+    Pressed(Button),
+    Released(Button),
+    // the new position of an axis, from -1.0 to 1.0
+    AxisChanged(Axis, f32),
+}
+
+// anything that produces gamepad events, a real controller or a script standing in for one
+pub trait GamepadSource {
+    // the next event that hasn't been handled yet, if any
+    fn poll(&mut self) -> Option<GamepadEvent>;
+}
+
+// plays back a fixed list of events, for machines without a controller
+pub struct ScriptedGamepad {
+    events: VecDeque<GamepadEvent>,
+}
+
+impl ScriptedGamepad {
+    pub fn new(events: Vec<GamepadEvent>) -> ScriptedGamepad {
+        return ScriptedGamepad {
+            events: events.into_iter().collect(),
+        };
+    }
+
+    pub fn push(&mut self, event: GamepadEvent) {
+        self.events.push_back(event);
+    }
+}
+
+impl GamepadSource for ScriptedGamepad {
+    fn poll(&mut self) -> Option<GamepadEvent> {
+        return self.events.pop_front();
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GamepadMapping {
+    pub buttons: Vec<(Button, Action)>,
+    // starts a new game once the current one is over
+    pub restart: Vec<Button>,
+    // how far the left stick has to be pushed before it counts, from 0.0 to 1.0
+    pub deadzone: f32,
+}
+
+impl Default for GamepadMapping {
+    fn default() -> GamepadMapping {
+        return GamepadMapping {
+            buttons: vec![
+                (Button::DPadLeft, Action::MoveLeft),
+                (Button::DPadRight, Action::MoveRight),
+                (Button::DPadDown, Action::SoftDrop),
+                (Button::DPadUp, Action::HardDrop),
+                (Button::East, Action::RotateClockwise),
+                (Button::South, Action::RotateCounterClockwise),
+                (Button::North, Action::Rotate180),
+                (Button::LeftTrigger, Action::Hold),
+                (Button::RightTrigger, Action::Hold),
+            ],
+            restart: vec![Button::Start],
+            deadzone: 0.5,
+        };
+    }
+}
+
+impl GamepadMapping {
+    pub fn action(&self, button: Button) -> Action {
+        match self.buttons.iter().find(|(bound, _)| *bound == button) {
+            Some((_, action)) => *action,
+            None => Action::None,
+        }
+    }
+}
+
+// feeds gamepad events into an InputHandler as presses and releases, so they go through the same
+// DAS and ARR as the keyboard, the left stick shifts and soft drops like the d-pad
+pub struct GamepadInput {
+    pub mapping: GamepadMapping,
+    // what each stick axis is currently holding down
+    stick_x: Action,
+    stick_y: Action,
+}
+
+impl GamepadInput {
+    pub fn new(mapping: GamepadMapping) -> GamepadInput {
+        return GamepadInput {
+            mapping,
+            stick_x: Action::None,
+            stick_y: Action::None,
+        };
+    }
+
+    // handles every pending event from the source, returning whether a restart button was pressed
+    pub fn poll(&mut self, source: &mut dyn GamepadSource, input: &mut InputHandler) -> bool {
+        let mut restart = false;
+        while let Some(event) = source.poll() {
+            match event {
+                GamepadEvent::Pressed(button) => {
+                    input.press(self.mapping.action(button));
+                    if self.mapping.restart.contains(&button) {
+                        restart = true;
+                    }
+                }
+                GamepadEvent::Released(button) => input.release(self.mapping.action(button)),
+                GamepadEvent::AxisChanged(axis, value) => {
+                    let action = match axis {
+                        Axis::LeftStickX => {
+                            self.stick_action(value, Action::MoveLeft, Action::MoveRight)
+                        }
+                        Axis::LeftStickY => {
+                            self.stick_action(value, Action::SoftDrop, Action::None)
+                        }
+                    };
+                    let held = match axis {
+                        Axis::LeftStickX => &mut self.stick_x,
+                        Axis::LeftStickY => &mut self.stick_y,
+                    };
+                    if *held != action {
+                        input.release(*held);
+                        input.press(action);
+                        *held = action;
+                    }
+                }
+            }
+        }
+        return restart;
+    }
+
+    fn stick_action(&self, value: f32, negative: Action, positive: Action) -> Action {
+        if value <= -self.mapping.deadzone {
+            return negative;
+        } else if value >= self.mapping.deadzone {
+            return positive;
+        }
+        return Action::None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tetris::input::InputSettings;
+    use std::time::Duration;
+
+    const NO_TIME: Duration = Duration::from_secs(0);
+
+    fn handler() -> InputHandler {
+        return InputHandler::new(InputSettings {
+            das: Duration::from_millis(100),
+            arr: Duration::from_millis(20),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn dpad_repeats_with_das_and_arr() {
+        let mut gamepad = ScriptedGamepad::new(vec![GamepadEvent::Pressed(Button::DPadLeft)]);
+        let mut gamepad_input = GamepadInput::new(GamepadMapping::default());
+        let mut input = handler();
+        assert!(!gamepad_input.poll(&mut gamepad, &mut input));
+        assert_eq!(input.update(NO_TIME, 0.0), vec![Action::MoveLeft]);
+        assert_eq!(input.update(Duration::from_millis(99), 0.0), vec![]);
+        // DAS charges, then ARR repeats every 20ms
+        assert_eq!(
+            input.update(Duration::from_millis(1), 0.0),
+            vec![Action::MoveLeft]
+        );
+        assert_eq!(
+            input.update(Duration::from_millis(40), 0.0),
+            vec![Action::MoveLeft, Action::MoveLeft]
+        );
+        gamepad.push(GamepadEvent::Released(Button::DPadLeft));
+        gamepad_input.poll(&mut gamepad, &mut input);
+        assert_eq!(input.update(Duration::from_millis(100), 0.0), vec![]);
+    }
+
+    #[test]
+    fn stick_inside_deadzone_does_nothing() {
+        let mut gamepad = ScriptedGamepad::new(vec![
+            GamepadEvent::AxisChanged(Axis::LeftStickX, -0.3),
+            GamepadEvent::AxisChanged(Axis::LeftStickY, -0.3),
+        ]);
+        let mut gamepad_input = GamepadInput::new(GamepadMapping::default());
+        let mut input = handler();
+        assert!(!gamepad_input.poll(&mut gamepad, &mut input));
+        assert_eq!(input.update(Duration::from_millis(500), 1.0), vec![]);
+    }
+
+    #[test]
+    fn stick_moving_across_swaps_direction() {
+        let mut gamepad =
+            ScriptedGamepad::new(vec![GamepadEvent::AxisChanged(Axis::LeftStickX, -0.9)]);
+        let mut gamepad_input = GamepadInput::new(GamepadMapping::default());
+        let mut input = handler();
+        gamepad_input.poll(&mut gamepad, &mut input);
+        assert_eq!(input.update(NO_TIME, 0.0), vec![Action::MoveLeft]);
+        gamepad.push(GamepadEvent::AxisChanged(Axis::LeftStickX, 0.9));
+        gamepad_input.poll(&mut gamepad, &mut input);
+        assert_eq!(input.update(NO_TIME, 0.0), vec![Action::MoveRight]);
+        assert_eq!(
+            input.update(Duration::from_millis(100), 0.0),
+            vec![Action::MoveRight]
+        );
+        // back to the middle releases the right, and the left was released already or it would
+        // take over again
+        gamepad.push(GamepadEvent::AxisChanged(Axis::LeftStickX, 0.0));
+        gamepad_input.poll(&mut gamepad, &mut input);
+        assert_eq!(input.update(Duration::from_millis(500), 0.0), vec![]);
+    }
+
+    #[test]
+    fn restart_button_is_reported() {
+        let mut gamepad = ScriptedGamepad::new(vec![GamepadEvent::Pressed(Button::South)]);
+        let mut gamepad_input = GamepadInput::new(GamepadMapping::default());
+        let mut input = handler();
+        assert!(!gamepad_input.poll(&mut gamepad, &mut input));
+        gamepad.push(GamepadEvent::Pressed(Button::Start));
+        assert!(gamepad_input.poll(&mut gamepad, &mut input));
+        assert!(!gamepad_input.poll(&mut gamepad, &mut input));
+    }
+}
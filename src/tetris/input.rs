@@ -0,0 +1,286 @@
+use crate::tetris::{Action, Rules, FRAME};
+use std::time::Duration;
+
+#[derive(Copy, Clone, Debug)]
+pub struct InputSettings {
+    // Delayed Auto Shift, how long a direction is held before it starts repeating
+    pub das: Duration,
+    // Auto Repeat Rate, time between repeated shifts, zero shifts straight to the wall
+    pub arr: Duration,
+    // soft drop gravity as a multiple of the current gravity, infinite drops straight down
+    pub soft_drop_factor: f64,
+    // how long auto repeat is held off after a new piece spawns, so a charged DAS doesn't
+    // carry the next piece away before the player reacts
+    pub das_cut: Duration,
+}
+
+impl Default for InputSettings {
+    fn default() -> InputSettings {
+        return InputSettings {
+            das: Duration::from_millis(167),
+            arr: Duration::from_millis(33),
+            soft_drop_factor: 20.0,
+            das_cut: Duration::from_secs(0),
+        };
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Direction {
+    Left,
+    Right,
+}
+
+// turns presses and releases of held inputs into the actions to feed PlaySpace::tick, repeating
+// shifts and soft drops while they're held
+pub struct InputHandler {
+    pub settings: InputSettings,
+    // ignores the key repeat sent by the OS for inputs that are already held
+    held: Vec<Action>,
+    // the most recently pressed direction that is still held
+    direction: Option<Direction>,
+    // how long the current direction has been held, it keeps charging between pieces
+    das_timer: Duration,
+    arr_timer: Duration,
+    das_cut_timer: Duration,
+    soft_drop_progress: f64,
+    // how many shifts or soft drops stand in for "as far as possible" with an instant auto repeat
+    // rate or soft drop, enough to cross the board
+    reach: usize,
+    pending: Vec<Action>,
+}
+
+impl InputHandler {
+    pub fn new(settings: InputSettings) -> InputHandler {
+        let rules = Rules::default();
+        return InputHandler {
+            settings,
+            held: Vec::new(),
+            direction: None,
+            das_timer: Duration::from_secs(0),
+            arr_timer: Duration::from_secs(0),
+            das_cut_timer: Duration::from_secs(0),
+            soft_drop_progress: 0.0,
+            reach: rules.width.max(rules.visible_height + rules.buffer_height),
+            pending: Vec::new(),
+        };
+    }
+
+    // sizes instant shifts and soft drops to a board with the given columns and rows, buffer rows
+    // included, the default board until this is called
+    pub fn set_board_size(&mut self, width: usize, height: usize) {
+        self.reach = width.max(height);
+    }
+
+    pub fn press(&mut self, action: Action) {
+        if action == Action::None || self.held.contains(&action) {
+            return;
+        }
+        self.held.push(action);
+        match action {
+            Action::MoveLeft => self.start_shift(Direction::Left),
+            Action::MoveRight => self.start_shift(Direction::Right),
+            Action::SoftDrop => {
+                self.pending.push(Action::SoftDrop);
+                self.soft_drop_progress = 0.0;
+            }
+            _ => self.pending.push(action),
+        }
+    }
+
+    pub fn release(&mut self, action: Action) {
+        self.held.retain(|&held| held != action);
+        let released = match action {
+            Action::MoveLeft => Direction::Left,
+            Action::MoveRight => Direction::Right,
+            _ => return,
+        };
+        if self.direction == Some(released) {
+            self.direction = None;
+            if self.held.contains(&Action::MoveLeft) {
+                self.start_shift(Direction::Left);
+            } else if self.held.contains(&Action::MoveRight) {
+                self.start_shift(Direction::Right);
+            }
+        }
+    }
+
+    // to be called whenever the play space spawns a piece, which starts the DAS cut
+    pub fn piece_spawned(&mut self) {
+        self.das_cut_timer = self.settings.das_cut;
+    }
+
+    // the actions to apply since the last update, gravity being the play space's current
+    // gravity in rows per frame
+    pub fn update(&mut self, elapsed: Duration, gravity: f64) -> Vec<Action> {
+        let mut actions = std::mem::take(&mut self.pending);
+        if let Some(direction) = self.direction {
+            let action = match direction {
+                Direction::Left => Action::MoveLeft,
+                Direction::Right => Action::MoveRight,
+            };
+            let charged_before = self.das_timer >= self.settings.das;
+            self.das_timer += elapsed;
+            if self.das_cut_timer > Duration::from_secs(0) {
+                self.das_cut_timer = self.das_cut_timer.saturating_sub(elapsed);
+            } else if self.das_timer >= self.settings.das {
+                if self.settings.arr == Duration::from_secs(0) {
+                    for _ in 0..self.reach {
+                        actions.push(action);
+                    }
+                } else {
+                    if charged_before {
+                        self.arr_timer += elapsed;
+                    } else {
+                        // the first repeat happens as DAS finishes charging
+                        self.arr_timer = self.das_timer - self.settings.das + self.settings.arr;
+                    }
+                    while self.arr_timer >= self.settings.arr {
+                        self.arr_timer -= self.settings.arr;
+                        actions.push(action);
+                    }
+                }
+            }
+        }
+        if self.held.contains(&Action::SoftDrop) {
+            let frames = elapsed.as_secs_f64() / FRAME.as_secs_f64();
+            self.soft_drop_progress += gravity * self.settings.soft_drop_factor * frames;
+            let mut drops = 0;
+            while self.soft_drop_progress >= 1.0 && drops < self.reach {
+                self.soft_drop_progress -= 1.0;
+                actions.push(Action::SoftDrop);
+                drops += 1;
+            }
+            if drops == self.reach {
+                self.soft_drop_progress = 0.0;
+            }
+        }
+        return actions;
+    }
+
+    fn start_shift(&mut self, direction: Direction) {
+        self.direction = Some(direction);
+        self.das_timer = Duration::from_secs(0);
+        self.arr_timer = Duration::from_secs(0);
+        self.pending.push(match direction {
+            Direction::Left => Action::MoveLeft,
+            Direction::Right => Action::MoveRight,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_TIME: Duration = Duration::from_secs(0);
+
+    fn settings() -> InputSettings {
+        return InputSettings {
+            das: Duration::from_millis(100),
+            arr: Duration::from_millis(20),
+            ..Default::default()
+        };
+    }
+
+    #[test]
+    fn zero_arr_shifts_across_the_board() {
+        let mut input = InputHandler::new(InputSettings {
+            arr: Duration::from_secs(0),
+            ..settings()
+        });
+        input.set_board_size(12, 22);
+        input.press(Action::MoveRight);
+        assert_eq!(input.update(NO_TIME, 0.0), vec![Action::MoveRight]);
+        assert_eq!(input.update(Duration::from_millis(99), 0.0), vec![]);
+        // the taller side of the board
+        let actions = input.update(Duration::from_millis(1), 0.0);
+        assert_eq!(actions, vec![Action::MoveRight; 22]);
+    }
+
+    #[test]
+    fn soft_drop_is_gravity_times_the_factor() {
+        let mut input = InputHandler::new(InputSettings {
+            soft_drop_factor: 4.0,
+            ..settings()
+        });
+        input.press(Action::SoftDrop);
+        assert_eq!(input.update(NO_TIME, 0.25), vec![Action::SoftDrop]);
+        // a row a frame, what's left of a row carries over
+        let actions = input.update(FRAME * 3 + Duration::from_millis(1), 0.25);
+        assert_eq!(actions, vec![Action::SoftDrop; 3]);
+        assert_eq!(input.update(FRAME, 0.25), vec![Action::SoftDrop]);
+        input.release(Action::SoftDrop);
+        assert_eq!(input.update(FRAME * 10, 0.25), vec![]);
+    }
+
+    #[test]
+    fn infinite_soft_drop_reaches_the_floor_of_a_tall_board() {
+        let mut input = InputHandler::new(InputSettings {
+            soft_drop_factor: f64::INFINITY,
+            ..settings()
+        });
+        input.set_board_size(10, 42);
+        input.press(Action::SoftDrop);
+        assert_eq!(input.update(FRAME, 0.02).len(), 43);
+        assert_eq!(input.update(FRAME, 0.02).len(), 42);
+    }
+
+    #[test]
+    fn das_cut_holds_off_repeats_after_a_spawn() {
+        let mut input = InputHandler::new(InputSettings {
+            das_cut: Duration::from_millis(50),
+            ..settings()
+        });
+        input.press(Action::MoveLeft);
+        assert_eq!(input.update(NO_TIME, 0.0), vec![Action::MoveLeft]);
+        assert_eq!(
+            input.update(Duration::from_millis(100), 0.0),
+            vec![Action::MoveLeft]
+        );
+        input.piece_spawned();
+        assert_eq!(input.update(Duration::from_millis(40), 0.0), vec![]);
+        assert_eq!(input.update(Duration::from_millis(10), 0.0), vec![]);
+        // still charged, so repeating picks up at the auto repeat rate
+        assert_eq!(
+            input.update(Duration::from_millis(20), 0.0),
+            vec![Action::MoveLeft]
+        );
+    }
+
+    #[test]
+    fn das_keeps_charging_through_the_entry_delay() {
+        let mut input = InputHandler::new(settings());
+        input.press(Action::MoveLeft);
+        assert_eq!(input.update(NO_TIME, 0.0), vec![Action::MoveLeft]);
+        assert_eq!(input.update(Duration::from_millis(60), 0.0), vec![]);
+        // the piece locked and the next one spawned while the direction was held
+        input.piece_spawned();
+        assert_eq!(
+            input.update(Duration::from_millis(40), 0.0),
+            vec![Action::MoveLeft]
+        );
+    }
+
+    #[test]
+    fn releasing_falls_back_to_the_other_held_direction() {
+        let mut input = InputHandler::new(settings());
+        input.press(Action::MoveLeft);
+        input.press(Action::MoveRight);
+        assert_eq!(
+            input.update(NO_TIME, 0.0),
+            vec![Action::MoveLeft, Action::MoveRight]
+        );
+        // the OS repeating the left key, which is already held, changes nothing
+        input.press(Action::MoveLeft);
+        assert_eq!(input.update(Duration::from_millis(50), 0.0), vec![]);
+        input.release(Action::MoveRight);
+        assert_eq!(input.update(NO_TIME, 0.0), vec![Action::MoveLeft]);
+        // with DAS charging again from the start
+        assert_eq!(input.update(Duration::from_millis(99), 0.0), vec![]);
+        assert_eq!(
+            input.update(Duration::from_millis(1), 0.0),
+            vec![Action::MoveLeft]
+        );
+    }
+}
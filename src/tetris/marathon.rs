@@ -0,0 +1,114 @@
+use crate::tetris::Rules;
+
+// how long a marathon lasts
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum MarathonGoal {
+    Lines150,
+    Lines200,
+    // until the stack tops out
+    Endless,
+}
+
+impl MarathonGoal {
+    pub fn from_name(name: &str) -> Option<MarathonGoal> {
+        match name.to_lowercase().as_str() {
+            "150" => Some(MarathonGoal::Lines150),
+            "200" => Some(MarathonGoal::Lines200),
+            "endless" => Some(MarathonGoal::Endless),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MarathonGoal::Lines150 => "150",
+            MarathonGoal::Lines200 => "200",
+            MarathonGoal::Endless => "endless",
+        }
+    }
+
+    pub fn lines(&self) -> Option<i32> {
+        match self {
+            MarathonGoal::Lines150 => Some(150),
+            MarathonGoal::Lines200 => Some(200),
+            MarathonGoal::Endless => None,
+        }
+    }
+}
+
+// the standard single player game, gravity speeds up with the level every 10 lines from the
+// starting level until the goal is reached
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Marathon {
+    pub start_level: i32,
+    pub goal: MarathonGoal,
+}
+
+impl Default for Marathon {
+    fn default() -> Marathon {
+        return Marathon {
+            start_level: 1,
+            goal: MarathonGoal::Lines150,
+        };
+    }
+}
+
+impl Marathon {
+    // the given rules played as this marathon
+    pub fn rules(&self, rules: Rules) -> Rules {
+        return Rules {
+            start_level: self.start_level,
+            line_goal: self.goal.lines(),
+            ..rules
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tetris::{Action, GameOverReason, GameState, PieceKind, PlaySpace};
+    use std::time::Duration;
+
+    // drops an I into a four row well, clearing all four
+    fn tetris(play_space: &mut PlaySpace) {
+        play_space.set_stack(&["#########."; 4], PieceKind::I, 1, (7, 3));
+        play_space.tick(Action::HardDrop, Duration::from_secs(0));
+    }
+
+    #[test]
+    fn starts_at_the_start_level() {
+        let marathon = Marathon {
+            start_level: 5,
+            ..Default::default()
+        };
+        let rules = marathon.rules(Default::default());
+        let mut play_space = PlaySpace::initialize_with_rules_and_seed(rules, 0);
+        assert_eq!(play_space.level(), 5);
+        tetris(&mut play_space);
+        tetris(&mut play_space);
+        assert_eq!(play_space.level(), 5);
+        tetris(&mut play_space);
+        assert_eq!(play_space.lines_cleared(), 12);
+        assert_eq!(play_space.level(), 6);
+    }
+
+    #[test]
+    fn ends_at_the_line_goal() {
+        let marathon = Marathon::default();
+        let rules = marathon.rules(Default::default());
+        let mut play_space = PlaySpace::initialize_with_rules_and_seed(rules, 0);
+        for _ in 0..37 {
+            tetris(&mut play_space);
+        }
+        assert_eq!(play_space.lines_cleared(), 148);
+        assert_eq!(play_space.state(), GameState::Playing);
+        tetris(&mut play_space);
+        assert_eq!(
+            play_space.state(),
+            GameState::GameOver {
+                reason: GameOverReason::GoalReached
+            }
+        );
+    }
+}
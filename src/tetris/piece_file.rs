@@ -0,0 +1,120 @@
+use crate::tetris::piece_set::PieceSet;
+use crate::tetris::PieceKind;
+use serde::Deserialize;
+use std::path::Path;
+
+// a piece set file lists the kicks tried for every rotation and then each piece, with the rows of
+// its spawn rotation read from the top left of its box, e.g.
+//
+// kicks = [[0, 0], [-1, 0], [1, 0]]
+//
+// [[piece]]
+// name = "T"
+// color = [0.5, 0.0, 0.5]
+// cells = [".#.", "###"]
+//
+// spawn = [x, y] moves a piece from the usual spawn position and rotations = [[...], [...], ...]
+// gives all four rotations instead of turning the spawn one
+#[derive(Deserialize)]
+struct PieceSetFile {
+    #[serde(default)]
+    kicks: Vec<[i32; 2]>,
+    piece: Vec<PieceDefinition>,
+}
+
+#[derive(Deserialize)]
+struct PieceDefinition {
+    name: String,
+    color: [f32; 3],
+    cells: Vec<String>,
+    #[serde(default)]
+    spawn: [i32; 2],
+    rotations: Option<Vec<Vec<String>>>,
+}
+
+// the sets that come with the game, played with --pieces and their name
+const BUILT_IN: [(&str, &str); 3] = [
+    ("standard", include_str!("../../pieces/standard.toml")),
+    ("pentomino", include_str!("../../pieces/pentomino.toml")),
+    ("beginner", include_str!("../../pieces/beginner.toml")),
+];
+
+pub struct LoadedPieces {
+    pub set: PieceSet,
+    // the colour each piece asks to be drawn in as red, green and blue from 0.0 to 1.0, the
+    // engine itself never looks at them
+    pub colors: Vec<(PieceKind, [f32; 3])>,
+}
+
+// a built-in set by name, otherwise the set in the file at that path
+pub fn load(name: &str) -> Result<LoadedPieces, String> {
+    if let Some((_, text)) = BUILT_IN.iter().find(|(built_in, _)| *built_in == name) {
+        return parse(text).map_err(|error| format!("couldn't parse {} pieces: {}", name, error));
+    }
+    let path = Path::new(name);
+    let text = std::fs::read_to_string(path)
+        .map_err(|error| format!("couldn't read {}: {}", path.display(), error))?;
+    return parse(&text).map_err(|error| format!("couldn't parse {}: {}", path.display(), error));
+}
+
+pub fn parse(text: &str) -> Result<LoadedPieces, String> {
+    let file: PieceSetFile = toml::from_str(text).map_err(|error| error.to_string())?;
+    let kicks = file.kicks.iter().map(|&[x, y]| (x, y)).collect();
+    let mut set = PieceSet::new(kicks);
+    let mut colors = Vec::new();
+    for piece in file.piece.iter() {
+        let kind = set.add(
+            &piece.name,
+            &piece.cells,
+            piece.rotations.as_deref(),
+            (piece.spawn[0], piece.spawn[1]),
+        )?;
+        colors.push((kind, piece.color));
+    }
+    if colors.is_empty() {
+        return Err("no pieces".to_string());
+    }
+    return Ok(LoadedPieces { set, colors });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tetris::rotation::{RotationSystem, RotationSystemKind};
+
+    #[test]
+    fn built_in_sets_load() {
+        for &(name, _) in BUILT_IN.iter() {
+            let loaded = load(name).unwrap();
+            assert_eq!(loaded.set.kinds().len(), loaded.colors.len());
+        }
+        assert_eq!(load("pentomino").unwrap().set.kinds().len(), 12);
+    }
+
+    #[test]
+    fn standard_set_turns_like_srs() {
+        let set = load("standard").unwrap().set;
+        let srs = RotationSystemKind::Srs.build();
+        let kinds = [
+            PieceKind::I,
+            PieceKind::T,
+            PieceKind::S,
+            PieceKind::Z,
+            PieceKind::J,
+            PieceKind::L,
+        ];
+        for &kind in kinds.iter() {
+            for rotation in 0..4 {
+                assert_eq!(set.map(kind, rotation), srs.map(kind, rotation));
+            }
+        }
+    }
+
+    #[test]
+    fn bad_files_are_refused() {
+        assert!(parse("kicks = []").is_err());
+        let empty = "[[piece]]\nname = \"x\"\ncolor = [1.0, 1.0, 1.0]\ncells = [\"..\"]";
+        assert!(parse(empty).is_err());
+        assert!(load("no/such/pieces.toml").is_err());
+    }
+}
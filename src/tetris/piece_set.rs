@@ -0,0 +1,207 @@
+use crate::tetris::rotation::{self, map_from_rows, RotationSystem};
+use crate::tetris::{PieceKind, PieceMap, SpaceState, BOX};
+
+#[derive(Clone, Debug)]
+struct PieceShape {
+    kind: PieceKind,
+    rotations: [PieceMap; 4],
+    // added to the usual spawn position
+    spawn_offset: (i32, i32),
+}
+
+// a set of pieces described as data rather than code, which stands in for the rotation system
+// when a game is played with it
+#[derive(Clone, Debug)]
+pub struct PieceSet {
+    pieces: Vec<PieceShape>,
+    // offsets tried in order for every rotation of every piece
+    kicks: Vec<(i32, i32)>,
+}
+
+impl PieceSet {
+    pub fn new(kicks: Vec<(i32, i32)>) -> PieceSet {
+        // with no kicks given pieces only turn in place
+        let kicks = if kicks.is_empty() {
+            vec![(0, 0)]
+        } else {
+            kicks
+        };
+        return PieceSet {
+            pieces: Vec::new(),
+            kicks,
+        };
+    }
+
+    // adds a piece from the rows of its spawn rotation, '#' marking a block, its box being as big
+    // as the rows are many or long. The other rotations are its box turned clockwise unless they
+    // are given, spawn rotation first. Pieces named after one of the seven tetrominoes are that
+    // kind, so they keep their colours and t-spins, any others are numbered in the order added.
+    pub fn add(
+        &mut self,
+        name: &str,
+        rows: &[String],
+        rotations: Option<&[Vec<String>]>,
+        spawn_offset: (i32, i32),
+    ) -> Result<PieceKind, String> {
+        let (spawn, size) = checked_map(name, rows)?;
+        let mut maps = [spawn; 4];
+        match rotations {
+            Some(rotations) if rotations.len() == 4 => {
+                for (rotation, rows) in rotations.iter().enumerate() {
+                    maps[rotation] = checked_map(name, rows)?.0;
+                }
+            }
+            Some(_) => return Err(format!("piece {} needs all 4 rotations", name)),
+            None => {
+                for rotation in 1..4 {
+                    maps[rotation] = turn_clockwise(&maps[rotation - 1], size);
+                }
+            }
+        }
+        let kind = match name {
+            "I" => PieceKind::I,
+            "O" => PieceKind::O,
+            "T" => PieceKind::T,
+            "S" => PieceKind::S,
+            "Z" => PieceKind::Z,
+            "J" => PieceKind::J,
+            "L" => PieceKind::L,
+            _ => PieceKind::Custom(self.custom_count()),
+        };
+        if self.pieces.iter().any(|piece| piece.kind == kind) {
+            return Err(format!("piece {} is in the set twice", name));
+        }
+        self.pieces.push(PieceShape {
+            kind,
+            rotations: maps,
+            spawn_offset,
+        });
+        return Ok(kind);
+    }
+
+    // every piece in the set, in the order added
+    pub fn kinds(&self) -> Vec<PieceKind> {
+        return self.pieces.iter().map(|piece| piece.kind).collect();
+    }
+
+    fn custom_count(&self) -> u8 {
+        return self
+            .pieces
+            .iter()
+            .filter(|piece| matches!(piece.kind, PieceKind::Custom(_)))
+            .count() as u8;
+    }
+
+    fn piece(&self, kind: PieceKind) -> Option<&PieceShape> {
+        return self.pieces.iter().find(|piece| piece.kind == kind);
+    }
+}
+
+impl RotationSystem for PieceSet {
+    fn map(&self, kind: PieceKind, rotation: usize) -> PieceMap {
+        match self.piece(kind) {
+            Some(piece) => piece.rotations[rotation],
+            None => [[SpaceState::Empty; BOX]; BOX],
+        }
+    }
+
+    fn spawn_position(&self, kind: PieceKind, width: usize, visible_height: usize) -> (i32, i32) {
+        let offset = self.piece(kind).map_or((0, 0), |piece| piece.spawn_offset);
+        let (x, y) = rotation::standard_spawn_position(width, visible_height);
+        return (x + offset.0, y + offset.1);
+    }
+
+    fn kicks(&self, _kind: PieceKind, _from: usize, _to: usize) -> &[(i32, i32)] {
+        return &self.kicks;
+    }
+}
+
+// the map of one rotation's rows and the size of the box they fill, refusing rows that don't fit
+// in BOX or have no blocks
+fn checked_map(name: &str, rows: &[String]) -> Result<(PieceMap, usize), String> {
+    let size = rows
+        .iter()
+        .map(|row| row.chars().count())
+        .chain(std::iter::once(rows.len()))
+        .max()
+        .unwrap_or(0);
+    if size > BOX {
+        return Err(format!("piece {} is bigger than {}x{}", name, BOX, BOX));
+    }
+    let map = map_from_rows(rows);
+    if map == [[SpaceState::Empty; BOX]; BOX] {
+        return Err(format!("piece {} has no blocks", name));
+    }
+    return Ok((map, size));
+}
+
+// a map turned a quarter clockwise about the middle of the size x size box in its top left
+fn turn_clockwise(map: &PieceMap, size: usize) -> PieceMap {
+    let mut turned = [[SpaceState::Empty; BOX]; BOX];
+    for i in 0..size {
+        for j in 0..size {
+            turned[j][size - 1 - i] = map[i][j];
+        }
+    }
+    return turned;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(rows: &[&str]) -> Vec<String> {
+        return rows.iter().map(|row| row.to_string()).collect();
+    }
+
+    #[test]
+    fn rotations_are_generated_clockwise() {
+        let mut set = PieceSet::new(vec![]);
+        let kind = set.add("T", &rows(&[".#.", "###", "..."]), None, (0, 0));
+        assert_eq!(kind, Ok(PieceKind::T));
+        assert_eq!(
+            set.map(PieceKind::T, 1),
+            map_from_rows(&[".#.", ".##", ".#."])
+        );
+        assert_eq!(
+            set.map(PieceKind::T, 3),
+            map_from_rows(&[".#.", "##.", ".#."])
+        );
+    }
+
+    #[test]
+    fn other_names_are_numbered() {
+        let mut set = PieceSet::new(vec![]);
+        assert_eq!(
+            set.add("mono", &rows(&["#"]), None, (0, 0)),
+            Ok(PieceKind::Custom(0))
+        );
+        assert_eq!(
+            set.add("I", &rows(&["####"]), None, (0, 0)),
+            Ok(PieceKind::I)
+        );
+        assert_eq!(
+            set.add("duo", &rows(&["##"]), None, (0, 0)),
+            Ok(PieceKind::Custom(1))
+        );
+        assert!(set.add("I", &rows(&["####"]), None, (0, 0)).is_err());
+    }
+
+    // adds a domino with the first count of its rotations given, the upright ones being turn
+    fn add_domino(set: &mut PieceSet, turn: &[&str], count: usize) -> Result<PieceKind, String> {
+        let cells = rows(&["##"]);
+        let rotations = [cells.clone(), rows(turn), cells.clone(), rows(turn)];
+        return set.add("domino", &cells, Some(&rotations[..count]), (0, 0));
+    }
+
+    #[test]
+    fn given_rotations_are_checked_like_the_cells() {
+        let mut set = PieceSet::new(vec![]);
+        assert!(add_domino(&mut set, &["######"], 4).is_err());
+        assert!(add_domino(&mut set, &[".."], 4).is_err());
+        assert!(add_domino(&mut set, &["#", "#"], 3).is_err());
+        assert!(add_domino(&mut set, &["#", "#"], 4).is_ok());
+        let tall = rows(&["#", "#", "#", "#", "#", "#"]);
+        assert!(set.add("tall", &tall, None, (0, 0)).is_err());
+    }
+}
@@ -0,0 +1,382 @@
+use crate::tetris::PieceKind;
+use rand::{Rng, RngCore};
+use std::collections::VecDeque;
+
+// the kinds that fall in a standard game, leaving out garbage
+const PIECES: [PieceKind; 7] = [
+    PieceKind::I,
+    PieceKind::O,
+    PieceKind::T,
+    PieceKind::S,
+    PieceKind::Z,
+    PieceKind::J,
+    PieceKind::L,
+];
+
+// rolls in 0..n, sampled as u32 so seeded sequences match between 32 and 64 bit machines
+fn roll(rng: &mut dyn RngCore, n: usize) -> usize {
+    return rng.gen_range(0..n as u32) as usize;
+}
+
+pub trait Randomizer {
+    // produces a new piece at the back of the sequence
+    fn generate(&mut self, rng: &mut dyn RngCore) -> PieceKind;
+
+    // pieces generated but not yet drawn
+    fn queue(&mut self) -> &mut VecDeque<PieceKind>;
+
+    fn next(&mut self, rng: &mut dyn RngCore) -> PieceKind {
+        if self.queue().is_empty() {
+            let piece = self.generate(rng);
+            self.queue().push_back(piece);
+        }
+        return self.queue().pop_front().unwrap();
+    }
+
+    // the next count pieces that will be drawn, without drawing them
+    fn peek(&mut self, count: usize, rng: &mut dyn RngCore) -> Vec<PieceKind> {
+        while self.queue().len() < count {
+            let piece = self.generate(rng);
+            self.queue().push_back(piece);
+        }
+        return self.queue().iter().take(count).copied().collect();
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RandomizerKind {
+    Uniform,
+    Bag7,
+    Bag14,
+    Tgm,
+    Tgm3,
+    Nes,
+}
+
+impl RandomizerKind {
+    pub fn from_name(name: &str) -> Option<RandomizerKind> {
+        match name.to_lowercase().as_str() {
+            "uniform" => Some(RandomizerKind::Uniform),
+            "7bag" | "bag7" => Some(RandomizerKind::Bag7),
+            "14bag" | "bag14" => Some(RandomizerKind::Bag14),
+            "tgm" => Some(RandomizerKind::Tgm),
+            "tgm3" | "35bag" => Some(RandomizerKind::Tgm3),
+            "nes" => Some(RandomizerKind::Nes),
+            _ => None,
+        }
+    }
+
+    pub fn build(&self) -> Box<dyn Randomizer> {
+        return self.build_for(&PIECES);
+    }
+
+    // a randomizer dealing the given pieces, the TGM ones only know the seven tetrominoes and
+    // fall back to a 7-bag style bag for any other set
+    pub fn build_for(&self, pieces: &[PieceKind]) -> Box<dyn Randomizer> {
+        let standard = pieces.len() == PIECES.len() && PIECES.iter().all(|p| pieces.contains(p));
+        match self {
+            RandomizerKind::Uniform => Box::new(Uniform::new(pieces)),
+            RandomizerKind::Bag7 => Box::new(Bag::new(1, pieces)),
+            RandomizerKind::Bag14 => Box::new(Bag::new(2, pieces)),
+            RandomizerKind::Tgm if standard => Box::new(Tgm::new()),
+            RandomizerKind::Tgm3 if standard => Box::new(Tgm3::new()),
+            RandomizerKind::Tgm | RandomizerKind::Tgm3 => Box::new(Bag::new(1, pieces)),
+            RandomizerKind::Nes => Box::new(Nes::new(pieces)),
+        }
+    }
+}
+
+// every piece equally likely on every draw
+pub struct Uniform {
+    pieces: Vec<PieceKind>,
+    queue: VecDeque<PieceKind>,
+}
+
+impl Uniform {
+    pub fn new(pieces: &[PieceKind]) -> Uniform {
+        return Uniform {
+            pieces: pieces.to_vec(),
+            queue: VecDeque::new(),
+        };
+    }
+}
+
+impl Randomizer for Uniform {
+    fn generate(&mut self, rng: &mut dyn RngCore) -> PieceKind {
+        return self.pieces[roll(rng, self.pieces.len())];
+    }
+
+    fn queue(&mut self) -> &mut VecDeque<PieceKind> {
+        return &mut self.queue;
+    }
+}
+
+// shuffles a number of copies of every piece and deals them out before refilling, one copy of
+// the seven tetrominoes is the guideline 7-bag and two the 14-bag
+pub struct Bag {
+    copies: usize,
+    pieces: Vec<PieceKind>,
+    bag: Vec<PieceKind>,
+    queue: VecDeque<PieceKind>,
+}
+
+impl Bag {
+    pub fn new(copies: usize, pieces: &[PieceKind]) -> Bag {
+        return Bag {
+            copies,
+            pieces: pieces.to_vec(),
+            bag: Vec::new(),
+            queue: VecDeque::new(),
+        };
+    }
+}
+
+impl Randomizer for Bag {
+    fn generate(&mut self, rng: &mut dyn RngCore) -> PieceKind {
+        if self.bag.is_empty() {
+            for _ in 0..self.copies {
+                self.bag.extend_from_slice(&self.pieces);
+            }
+            for i in (1..self.bag.len()).rev() {
+                self.bag.swap(i, roll(rng, i + 1));
+            }
+        }
+        return self.bag.pop().unwrap();
+    }
+
+    fn queue(&mut self) -> &mut VecDeque<PieceKind> {
+        return &mut self.queue;
+    }
+}
+
+// TGM2's history randomizer, rerolls up to 6 times when a piece is among the last 4 dealt
+pub struct Tgm {
+    history: [PieceKind; 4],
+    first: bool,
+    queue: VecDeque<PieceKind>,
+}
+
+impl Default for Tgm {
+    fn default() -> Tgm {
+        return Tgm::new();
+    }
+}
+
+impl Tgm {
+    pub fn new() -> Tgm {
+        return Tgm {
+            history: [PieceKind::Z, PieceKind::S, PieceKind::S, PieceKind::Z],
+            first: true,
+            queue: VecDeque::new(),
+        };
+    }
+}
+
+// the first piece of a TGM game is never an S, Z or O
+fn tgm_first_piece(rng: &mut dyn RngCore) -> PieceKind {
+    let first = [PieceKind::I, PieceKind::J, PieceKind::L, PieceKind::T];
+    return first[roll(rng, first.len())];
+}
+
+fn push_history(history: &mut [PieceKind; 4], piece: PieceKind) {
+    history.rotate_left(1);
+    history[3] = piece;
+}
+
+impl Randomizer for Tgm {
+    fn generate(&mut self, rng: &mut dyn RngCore) -> PieceKind {
+        let mut piece = PieceKind::I;
+        if self.first {
+            self.first = false;
+            piece = tgm_first_piece(rng);
+        } else {
+            for _ in 0..6 {
+                piece = PIECES[roll(rng, PIECES.len())];
+                if !self.history.contains(&piece) {
+                    break;
+                }
+            }
+        }
+        push_history(&mut self.history, piece);
+        return piece;
+    }
+
+    fn queue(&mut self) -> &mut VecDeque<PieceKind> {
+        return &mut self.queue;
+    }
+}
+
+// TGM3's 35-bag, a history randomizer drawing from a pool that is biased towards whichever
+// piece has gone longest without appearing
+pub struct Tgm3 {
+    pool: Vec<PieceKind>,
+    order: Vec<PieceKind>,
+    history: [PieceKind; 4],
+    first: bool,
+    queue: VecDeque<PieceKind>,
+}
+
+impl Default for Tgm3 {
+    fn default() -> Tgm3 {
+        return Tgm3::new();
+    }
+}
+
+impl Tgm3 {
+    pub fn new() -> Tgm3 {
+        let mut pool = Vec::new();
+        for _ in 0..5 {
+            pool.extend_from_slice(&PIECES);
+        }
+        return Tgm3 {
+            pool,
+            order: Vec::new(),
+            history: [PieceKind::S, PieceKind::Z, PieceKind::S, PieceKind::Z],
+            first: true,
+            queue: VecDeque::new(),
+        };
+    }
+}
+
+impl Randomizer for Tgm3 {
+    fn generate(&mut self, rng: &mut dyn RngCore) -> PieceKind {
+        if self.first {
+            self.first = false;
+            let piece = tgm_first_piece(rng);
+            push_history(&mut self.history, piece);
+            return piece;
+        }
+        let mut index = 0;
+        let mut piece = PieceKind::I;
+        for attempt in 0..6 {
+            index = roll(rng, self.pool.len());
+            piece = self.pool[index];
+            if !self.history.contains(&piece) || attempt == 5 {
+                break;
+            }
+            if !self.order.is_empty() {
+                self.pool[index] = self.order[0];
+            }
+        }
+        self.order.retain(|&p| p != piece);
+        self.order.push(piece);
+        self.pool[index] = self.order[0];
+        push_history(&mut self.history, piece);
+        return piece;
+    }
+
+    fn queue(&mut self) -> &mut VecDeque<PieceKind> {
+        return &mut self.queue;
+    }
+}
+
+// NES Tetris rolls an eighth dummy value, and rerolls once over every piece when it gets
+// either that or a repeat of the previous piece
+pub struct Nes {
+    pieces: Vec<PieceKind>,
+    previous: Option<PieceKind>,
+    queue: VecDeque<PieceKind>,
+}
+
+impl Nes {
+    pub fn new(pieces: &[PieceKind]) -> Nes {
+        return Nes {
+            pieces: pieces.to_vec(),
+            previous: None,
+            queue: VecDeque::new(),
+        };
+    }
+}
+
+impl Randomizer for Nes {
+    fn generate(&mut self, rng: &mut dyn RngCore) -> PieceKind {
+        let count = self.pieces.len();
+        let first = roll(rng, count + 1);
+        let piece = if first < count && Some(self.pieces[first]) != self.previous {
+            self.pieces[first]
+        } else {
+            self.pieces[roll(rng, count)]
+        };
+        self.previous = Some(piece);
+        return piece;
+    }
+
+    fn queue(&mut self) -> &mut VecDeque<PieceKind> {
+        return &mut self.queue;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+    use std::collections::HashSet;
+
+    fn draw(randomizer: &mut dyn Randomizer, count: usize, seed: u64) -> Vec<PieceKind> {
+        let mut rng = Pcg64::seed_from_u64(seed);
+        return (0..count).map(|_| randomizer.next(&mut rng)).collect();
+    }
+
+    #[test]
+    fn bag7_deals_every_piece_once_per_seven() {
+        let pieces = draw(&mut Bag::new(1, &PIECES), 7 * 50, 1);
+        for bag in pieces.chunks(7) {
+            let kinds: HashSet<_> = bag.iter().collect();
+            assert_eq!(kinds.len(), 7, "{:?}", bag);
+        }
+    }
+
+    #[test]
+    fn bag14_deals_every_piece_twice_per_fourteen() {
+        let pieces = draw(&mut Bag::new(2, &PIECES), 14 * 50, 2);
+        for bag in pieces.chunks(14) {
+            for kind in PIECES.iter() {
+                assert_eq!(bag.iter().filter(|&p| p == kind).count(), 2, "{:?}", bag);
+            }
+        }
+    }
+
+    #[test]
+    fn bag_deals_the_given_pieces() {
+        let set = [
+            PieceKind::Custom(0),
+            PieceKind::Custom(1),
+            PieceKind::Custom(2),
+        ];
+        let pieces = draw(&mut Bag::new(1, &set), 3 * 20, 3);
+        for bag in pieces.chunks(3) {
+            let kinds: HashSet<_> = bag.iter().collect();
+            assert_eq!(kinds.len(), 3, "{:?}", bag);
+        }
+    }
+
+    #[test]
+    fn tgm_first_piece_is_never_s_z_or_o() {
+        for seed in 0..200 {
+            let first = draw(&mut Tgm::new(), 1, seed)[0];
+            assert!(![PieceKind::S, PieceKind::Z, PieceKind::O].contains(&first));
+            let first = draw(&mut Tgm3::new(), 1, seed)[0];
+            assert!(![PieceKind::S, PieceKind::Z, PieceKind::O].contains(&first));
+        }
+    }
+
+    // how often a piece is one of the four before it, around 43% for an unbiased randomizer
+    fn repeat_rate(pieces: &[PieceKind]) -> f64 {
+        let repeats = (4..pieces.len())
+            .filter(|&n| pieces[n - 4..n].contains(&pieces[n]))
+            .count();
+        return repeats as f64 / (pieces.len() - 4) as f64;
+    }
+
+    #[test]
+    fn tgm_history_rerolls_recent_pieces() {
+        // six rerolls against at most four of seven pieces leave a repeat well under 4%
+        let pieces = draw(&mut Tgm::new(), 10000, 4);
+        assert!(repeat_rate(&pieces) < 0.04, "{}", repeat_rate(&pieces));
+        let pieces = draw(&mut Tgm3::new(), 10000, 5);
+        assert!(repeat_rate(&pieces) < 0.04, "{}", repeat_rate(&pieces));
+        let pieces = draw(&mut Uniform::new(&PIECES), 10000, 6);
+        assert!(repeat_rate(&pieces) > 0.3, "{}", repeat_rate(&pieces));
+    }
+}
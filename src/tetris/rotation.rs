@@ -0,0 +1,395 @@
+use crate::tetris::{PieceKind, PieceMap, SpaceState, BOX};
+
+// shapes are listed per rotation (spawn, clockwise, 180, counter clockwise) as rows of a 4x4
+// box read from the top left, '#' marking a block
+type Shapes = [[&'static str; 4]; 4];
+
+pub trait RotationSystem {
+    // the block map of a piece in the given rotation
+    fn map(&self, kind: PieceKind, rotation: usize) -> PieceMap;
+
+    fn spawn_rotation(&self, _kind: PieceKind) -> usize {
+        return 0;
+    }
+
+    // position of the top left of the box when a piece enters the play space
+    fn spawn_position(&self, _kind: PieceKind, width: usize, visible_height: usize) -> (i32, i32) {
+        return standard_spawn_position(width, visible_height);
+    }
+
+    // (x, y) offsets tried in order when rotating, the first one that fits is used
+    fn kicks(&self, kind: PieceKind, from: usize, to: usize) -> &[(i32, i32)];
+
+    // whether a t-spin made with the given kick counts as a full one even with only one of the
+    // corners the T points at filled
+    fn upgrades_t_spin(&self, _kind: PieceKind, _from: usize, _to: usize, _kick: usize) -> bool {
+        return false;
+    }
+}
+
+// just above the visible rows and around the middle, (4, 21) on a standard board
+pub fn standard_spawn_position(width: usize, visible_height: usize) -> (i32, i32) {
+    let x = (width as i32 / 2 - 1).min(width as i32 - 4);
+    return (x, visible_height as i32 + 1);
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RotationSystemKind {
+    Srs,
+    Ars,
+    Nrs,
+    Sega,
+}
+
+impl RotationSystemKind {
+    pub fn from_name(name: &str) -> Option<RotationSystemKind> {
+        match name.to_lowercase().as_str() {
+            "srs" => Some(RotationSystemKind::Srs),
+            "ars" => Some(RotationSystemKind::Ars),
+            "nrs" => Some(RotationSystemKind::Nrs),
+            "sega" => Some(RotationSystemKind::Sega),
+            _ => None,
+        }
+    }
+
+    pub fn build(&self) -> Box<dyn RotationSystem> {
+        match self {
+            RotationSystemKind::Srs => Box::new(Srs),
+            RotationSystemKind::Ars => Box::new(Ars),
+            RotationSystemKind::Nrs => Box::new(Nrs),
+            RotationSystemKind::Sega => Box::new(Sega),
+        }
+    }
+}
+
+// garbage never falls and custom pieces only exist in their PieceSet, so neither has a shape
+// here
+const NO_SHAPE: Shapes = [["...."; 4]; 4];
+
+pub fn map_from_rows<S: AsRef<str>>(rows: &[S]) -> PieceMap {
+    let mut map = [[SpaceState::Empty; BOX]; BOX];
+    for (i, row) in rows.iter().take(BOX).enumerate() {
+        for (j, block) in row.as_ref().chars().take(BOX).enumerate() {
+            if block == '#' {
+                map[i][j] = SpaceState::FallingTetromino;
+            }
+        }
+    }
+    return map;
+}
+
+// Super Rotation System, the guideline standard
+pub struct Srs;
+
+const SRS_I: Shapes = [
+    ["....", "####", "....", "...."],
+    ["..#.", "..#.", "..#.", "..#."],
+    ["....", "....", "####", "...."],
+    [".#..", ".#..", ".#..", ".#.."],
+];
+const SRS_O: Shapes = [
+    [".##.", ".##.", "....", "...."],
+    [".##.", ".##.", "....", "...."],
+    [".##.", ".##.", "....", "...."],
+    [".##.", ".##.", "....", "...."],
+];
+const SRS_T: Shapes = [
+    [".#..", "###.", "....", "...."],
+    [".#..", ".##.", ".#..", "...."],
+    ["....", "###.", ".#..", "...."],
+    [".#..", "##..", ".#..", "...."],
+];
+const SRS_S: Shapes = [
+    [".##.", "##..", "....", "...."],
+    [".#..", ".##.", "..#.", "...."],
+    ["....", ".##.", "##..", "...."],
+    ["#...", "##..", ".#..", "...."],
+];
+const SRS_Z: Shapes = [
+    ["##..", ".##.", "....", "...."],
+    ["..#.", ".##.", ".#..", "...."],
+    ["....", "##..", ".##.", "...."],
+    [".#..", "##..", "#...", "...."],
+];
+const SRS_J: Shapes = [
+    ["#...", "###.", "....", "...."],
+    [".##.", ".#..", ".#..", "...."],
+    ["....", "###.", "..#.", "...."],
+    [".#..", ".#..", "##..", "...."],
+];
+const SRS_L: Shapes = [
+    ["..#.", "###.", "....", "...."],
+    [".#..", ".#..", ".##.", "...."],
+    ["....", "###.", "#...", "...."],
+    ["##..", ".#..", ".#..", "...."],
+];
+
+// kick offsets as (x, y) with y pointing up, indexed by [from * 2] for clockwise and
+// [from * 2 + 1] for counter clockwise: 0->R, 0->L, R->2, R->0, 2->L, 2->R, L->0, L->2
+const SRS_JLSTZ_KICKS: [[(i32, i32); 5]; 8] = [
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+];
+
+const SRS_I_KICKS: [[(i32, i32); 5]; 8] = [
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+];
+
+// SRS has no official 180 kicks, these are the widely used SRS+ ones, indexed by [from]
+const SRS_180_KICKS: [[(i32, i32); 6]; 4] = [
+    [(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)],
+    [(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)],
+    [(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)],
+    [(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)],
+];
+
+impl RotationSystem for Srs {
+    fn map(&self, kind: PieceKind, rotation: usize) -> PieceMap {
+        let shapes = match kind {
+            PieceKind::I => &SRS_I,
+            PieceKind::O => &SRS_O,
+            PieceKind::T => &SRS_T,
+            PieceKind::S => &SRS_S,
+            PieceKind::Z => &SRS_Z,
+            PieceKind::J => &SRS_J,
+            PieceKind::L => &SRS_L,
+            PieceKind::Garbage | PieceKind::Custom(_) => &NO_SHAPE,
+        };
+        return map_from_rows(&shapes[rotation]);
+    }
+
+    fn kicks(&self, kind: PieceKind, from: usize, to: usize) -> &[(i32, i32)] {
+        if let PieceKind::O = kind {
+            return &[(0, 0)];
+        }
+        if to == (from + 2) % 4 {
+            return &SRS_180_KICKS[from];
+        }
+        let index = if to == (from + 1) % 4 {
+            from * 2
+        } else {
+            from * 2 + 1
+        };
+        if let PieceKind::I = kind {
+            return &SRS_I_KICKS[index];
+        }
+        return &SRS_JLSTZ_KICKS[index];
+    }
+
+    // the last kick of a quarter turn is the TST/Fin kick that moves the T one column and two
+    // rows, the 180 kick moving it as far doesn't count
+    fn upgrades_t_spin(&self, kind: PieceKind, from: usize, to: usize, kick: usize) -> bool {
+        return kind == PieceKind::T && to != (from + 2) % 4 && kick == 4;
+    }
+}
+
+// Arika Rotation System from TGM, pieces spawn flat side up and rest against the bottom of
+// their box. TGM's rule refusing kicks blocked only by the centre column isn't modelled.
+pub struct Ars;
+
+const ARS_I: Shapes = [
+    ["....", "####", "....", "...."],
+    ["..#.", "..#.", "..#.", "..#."],
+    ["....", "####", "....", "...."],
+    ["..#.", "..#.", "..#.", "..#."],
+];
+const ARS_O: Shapes = [
+    ["....", ".##.", ".##.", "...."],
+    ["....", ".##.", ".##.", "...."],
+    ["....", ".##.", ".##.", "...."],
+    ["....", ".##.", ".##.", "...."],
+];
+const ARS_T: Shapes = [
+    ["....", "###.", ".#..", "...."],
+    [".#..", "##..", ".#..", "...."],
+    ["....", ".#..", "###.", "...."],
+    [".#..", ".##.", ".#..", "...."],
+];
+const ARS_S: Shapes = [
+    ["....", ".##.", "##..", "...."],
+    ["#...", "##..", ".#..", "...."],
+    ["....", ".##.", "##..", "...."],
+    ["#...", "##..", ".#..", "...."],
+];
+const ARS_Z: Shapes = [
+    ["....", "##..", ".##.", "...."],
+    ["..#.", ".##.", ".#..", "...."],
+    ["....", "##..", ".##.", "...."],
+    ["..#.", ".##.", ".#..", "...."],
+];
+const ARS_J: Shapes = [
+    ["....", "###.", "..#.", "...."],
+    [".#..", ".#..", "##..", "...."],
+    ["....", "#...", "###.", "...."],
+    [".##.", ".#..", ".#..", "...."],
+];
+const ARS_L: Shapes = [
+    ["....", "###.", "#...", "...."],
+    ["##..", ".#..", ".#..", "...."],
+    ["....", "..#.", "###.", "...."],
+    [".#..", ".#..", ".##.", "...."],
+];
+
+fn ars_map(kind: PieceKind, rotation: usize) -> PieceMap {
+    let shapes = match kind {
+        PieceKind::I => &ARS_I,
+        PieceKind::O => &ARS_O,
+        PieceKind::T => &ARS_T,
+        PieceKind::S => &ARS_S,
+        PieceKind::Z => &ARS_Z,
+        PieceKind::J => &ARS_J,
+        PieceKind::L => &ARS_L,
+        PieceKind::Garbage | PieceKind::Custom(_) => &NO_SHAPE,
+    };
+    return map_from_rows(&shapes[rotation]);
+}
+
+impl RotationSystem for Ars {
+    fn map(&self, kind: PieceKind, rotation: usize) -> PieceMap {
+        return ars_map(kind, rotation);
+    }
+
+    fn kicks(&self, kind: PieceKind, _from: usize, _to: usize) -> &[(i32, i32)] {
+        if let PieceKind::I = kind {
+            return &[(0, 0)];
+        }
+        return &[(0, 0), (1, 0), (-1, 0)];
+    }
+}
+
+// Nintendo Rotation System as in NES Tetris, right handed and without any kicks
+pub struct Nrs;
+
+const NRS_I: Shapes = [
+    ["....", "....", "####", "...."],
+    ["..#.", "..#.", "..#.", "..#."],
+    ["....", "....", "####", "...."],
+    ["..#.", "..#.", "..#.", "..#."],
+];
+const NRS_T: Shapes = [
+    ["....", "###.", ".#..", "...."],
+    [".#..", "##..", ".#..", "...."],
+    [".#..", "###.", "....", "...."],
+    [".#..", ".##.", ".#..", "...."],
+];
+const NRS_S: Shapes = [
+    ["....", ".##.", "##..", "...."],
+    [".#..", ".##.", "..#.", "...."],
+    ["....", ".##.", "##..", "...."],
+    [".#..", ".##.", "..#.", "...."],
+];
+const NRS_J: Shapes = [
+    ["....", "###.", "..#.", "...."],
+    [".#..", ".#..", "##..", "...."],
+    ["#...", "###.", "....", "...."],
+    [".##.", ".#..", ".#..", "...."],
+];
+const NRS_L: Shapes = [
+    ["....", "###.", "#...", "...."],
+    ["##..", ".#..", ".#..", "...."],
+    ["..#.", "###.", "....", "...."],
+    [".#..", ".#..", ".##.", "...."],
+];
+
+impl RotationSystem for Nrs {
+    fn map(&self, kind: PieceKind, rotation: usize) -> PieceMap {
+        let shapes = match kind {
+            PieceKind::I => &NRS_I,
+            PieceKind::O => &ARS_O,
+            PieceKind::T => &NRS_T,
+            PieceKind::S => &NRS_S,
+            PieceKind::Z => &ARS_Z,
+            PieceKind::J => &NRS_J,
+            PieceKind::L => &NRS_L,
+            PieceKind::Garbage | PieceKind::Custom(_) => &NO_SHAPE,
+        };
+        return map_from_rows(&shapes[rotation]);
+    }
+
+    fn kicks(&self, _kind: PieceKind, _from: usize, _to: usize) -> &[(i32, i32)] {
+        return &[(0, 0)];
+    }
+}
+
+// Sega's arcade rotation, the shapes ARS was built on but without any kicks
+pub struct Sega;
+
+impl RotationSystem for Sega {
+    fn map(&self, kind: PieceKind, rotation: usize) -> PieceMap {
+        return ars_map(kind, rotation);
+    }
+
+    fn kicks(&self, _kind: PieceKind, _from: usize, _to: usize) -> &[(i32, i32)] {
+        return &[(0, 0)];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tetris::event::GameEvent;
+    use crate::tetris::{Action, PieceKind, PlaySpace};
+    use std::time::Duration;
+
+    const NO_TIME: Duration = Duration::from_secs(0);
+
+    fn kick_index(play_space: &mut PlaySpace) -> Option<usize> {
+        return play_space
+            .drain_events()
+            .iter()
+            .find_map(|event| match event {
+                GameEvent::Rotated { kick_index, .. } => Some(*kick_index),
+                _ => None,
+            });
+    }
+
+    #[test]
+    fn srs_i_kicks_off_the_left_wall() {
+        // upright against the wall, lying flat needs the column to its left
+        let mut play_space = PlaySpace::with_stack(&[], PieceKind::I, 3, (-1, 10));
+        play_space.tick(Action::RotateClockwise, NO_TIME);
+        assert_eq!(play_space.current_tetromino_rotation(), 0);
+        assert_eq!(play_space.falling_position(), (0, 10));
+        assert_eq!(kick_index(&mut play_space), Some(1));
+    }
+
+    #[test]
+    fn srs_t_kicks_into_a_t_spin_double_slot() {
+        let rows = ["###..#....", "###...####", "####.#####"];
+        let mut play_space = PlaySpace::with_stack(&rows, PieceKind::T, 1, (2, 3));
+        play_space.tick(Action::RotateClockwise, NO_TIME);
+        assert_eq!(play_space.current_tetromino_rotation(), 2);
+        assert_eq!(play_space.falling_position(), (3, 2));
+        assert_eq!(kick_index(&mut play_space), Some(2));
+    }
+
+    #[test]
+    fn srs_180_kicks_up_off_the_floor() {
+        // flat on the floor, pointing down needs a row below it
+        let mut play_space = PlaySpace::with_stack(&[], PieceKind::T, 0, (4, 1));
+        play_space.tick(Action::Rotate180, NO_TIME);
+        assert_eq!(play_space.current_tetromino_rotation(), 2);
+        assert_eq!(play_space.falling_position(), (4, 2));
+        assert_eq!(kick_index(&mut play_space), Some(1));
+    }
+
+    #[test]
+    fn srs_o_never_kicks() {
+        let mut play_space = PlaySpace::with_stack(&[], PieceKind::O, 0, (-1, 1));
+        play_space.tick(Action::RotateClockwise, NO_TIME);
+        assert_eq!(play_space.current_tetromino_rotation(), 1);
+        assert_eq!(play_space.falling_position(), (-1, 1));
+    }
+}